@@ -1,4 +1,24 @@
 pub mod aws_detect;
 pub mod aws_metrics;
 pub mod aws_summary;
+pub mod pivot_keywords;
 pub mod update;
+
+use std::process::ExitCode;
+
+/// The result of a command, which is used as the exit code of the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Finished without detections at or above --fail-on-level
+    Clean = 0,
+    /// Could not finish because of an error (ex: invalid options, no rules loaded)
+    Error = 1,
+    /// Detections at or above --fail-on-level were found
+    Detected = 2,
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
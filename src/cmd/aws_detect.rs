@@ -1,10 +1,33 @@
+use crate::cmd::Status;
+use crate::core::aggregate::{AGGREGATION_COUNT, AGGREGATION_LAST_TIME, DetectionAggregator};
+use crate::core::checkpoint::Checkpoint;
 use crate::core::color::SuzakuColor;
 use crate::core::color::SuzakuColor::{Cyan, Green, Orange, Red, White, Yellow};
+use crate::core::redact::Redactor;
 use crate::core::rules;
-use crate::core::scan::{scan_directory, scan_file};
-use crate::core::util::{get_json_writer, get_writer, output_path_info, p};
+use crate::core::scan::{follow_input, scan_input};
+use crate::core::sort::{DetectionSorter, SORT_MEMORY_BUDGET};
+use crate::core::state::{ScanState, filter_options};
+use crate::core::util::{open_output_file, output_path_info, p};
 use crate::option::cli::{AwsCtTimelineOptions, CommonOptions};
 use crate::option::geoip::GeoIPSearch;
+use crate::option::timefiler::parse_offset;
+use crate::option::timeformat::TimeFormatter;
+use crate::output::elastic;
+use crate::output::elastic::ElasticWriter;
+use crate::output::html::HtmlReport;
+use crate::output::http::HttpSink;
+use crate::output::insight::InsightSummary;
+use crate::output::ocsf::OcsfWriter;
+use crate::output::parquet::ParquetWriter;
+use crate::output::pivot;
+use crate::output::pivot::PivotKeywords;
+use crate::output::sqlite::SqliteWriter;
+use crate::output::stix;
+use crate::output::stix::StixBundle;
+use crate::output::summary::write_summary_json;
+use crate::output::syslog::{SyslogFormat, SyslogTarget, SyslogWriter};
+use crate::output::timesketch::TimesketchWriter;
 use chrono::{DateTime, Utc};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
@@ -12,28 +35,47 @@ use comfy_table::{Cell, Table, TableComponent};
 use csv::Writer;
 use krapslog::{build_sparkline, build_time_markers};
 use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sigma_rust::{Event, Rule};
+use sigma_rust::{Event, Rule, event_from_json};
 use std::cmp::min;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
+use std::io;
 use std::io::{BufRead, BufReader};
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 use terminal_size::{Width, terminal_size};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DetectionSummary {
-    pub author_titles: HashMap<String, HashSet<String>>,
+    pub author_titles: BTreeMap<String, BTreeSet<String>>,
+    #[serde(skip)]
     pub timestamps: Vec<i64>,
     pub total_events: usize,
     pub event_with_hits: usize,
-    pub dates_with_hits: HashMap<String, HashMap<String, usize>>,
-    pub level_with_hits: HashMap<String, HashMap<String, usize>>,
+    pub duplicate_events: usize,
+    pub category_events: BTreeMap<String, usize>,
+    pub category_hits: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "InsightSummary::is_empty")]
+    pub insights: InsightSummary,
+    pub dates_with_hits: BTreeMap<String, BTreeMap<String, usize>>,
+    pub level_with_hits: BTreeMap<String, BTreeMap<String, usize>>,
     pub first_event_time: Option<DateTime<Utc>>,
     pub last_event_time: Option<DateTime<Utc>>,
 }
 
+impl DetectionSummary {
+    /// Returns true if there are detections of rules at or above the level (see --fail-on-level).
+    pub fn has_hits_at_or_above(&self, level: &str) -> bool {
+        let min = rules::level_to_int(level);
+        self.level_with_hits
+            .iter()
+            .any(|(level, hits)| rules::level_to_int(level) >= min && !hits.is_empty())
+    }
+}
+
 #[derive(Debug)]
 pub enum OutputType {
     Csv,
@@ -41,6 +83,12 @@ pub enum OutputType {
     Jsonl,
     CsvAndJson,
     CsvAndJsonl,
+    Sqlite,
+    Elastic,
+    Ocsf,
+    TimesketchCsv,
+    TimesketchJsonl,
+    Parquet,
 }
 
 impl OutputType {
@@ -51,9 +99,46 @@ impl OutputType {
             3 => Some(OutputType::Jsonl),
             4 => Some(OutputType::CsvAndJson),
             5 => Some(OutputType::CsvAndJsonl),
+            6 => Some(OutputType::Sqlite),
+            7 => Some(OutputType::Elastic),
+            8 => Some(OutputType::Ocsf),
+            9 => Some(OutputType::TimesketchCsv),
+            10 => Some(OutputType::TimesketchJsonl),
+            11 => Some(OutputType::Parquet),
             _ => None,
         }
     }
+
+    /// Returns the files written for this output type. The extension of `output` is replaced
+    /// when it does not match the format (ex: `-o results -t 6` writes results.db).
+    pub fn output_paths(&self, output: &Path) -> Vec<PathBuf> {
+        let path = |ext| with_extension(output, ext);
+        match self {
+            OutputType::Csv => vec![path("csv")],
+            OutputType::Json => vec![path("json")],
+            OutputType::Jsonl => vec![path("jsonl")],
+            OutputType::CsvAndJson => vec![path("csv"), path("json")],
+            OutputType::CsvAndJsonl => vec![path("csv"), path("jsonl")],
+            OutputType::Sqlite => vec![path("db")],
+            OutputType::Elastic => {
+                let ndjson_path = path("ndjson");
+                let template_path = elastic::index_template_path(&ndjson_path);
+                vec![ndjson_path, template_path]
+            }
+            OutputType::Ocsf => vec![path("jsonl")],
+            OutputType::TimesketchCsv => vec![path("csv")],
+            OutputType::TimesketchJsonl => vec![path("jsonl")],
+            OutputType::Parquet => vec![path("parquet")],
+        }
+    }
+}
+
+fn with_extension(path: &Path, ext: &str) -> PathBuf {
+    let mut path = path.to_path_buf();
+    if path.extension().and_then(|e| e.to_str()) != Some(ext) {
+        path.set_extension(ext);
+    }
+    path
 }
 
 pub struct Writers {
@@ -61,6 +146,148 @@ pub struct Writers {
     json: Option<BufWriter<Box<dyn Write>>>,
     jsonl: Option<BufWriter<Box<dyn Write>>>,
     std: Option<BufferWriter>,
+    sqlite: Option<SqliteWriter>,
+    elastic: Option<ElasticWriter>,
+    ocsf: Option<OcsfWriter>,
+    timesketch: Option<TimesketchWriter>,
+    parquet: Option<ParquetWriter>,
+    html: Option<HtmlReport>,
+    syslog: Option<SyslogWriter>,
+    http: Option<HttpSink>,
+    stix: Option<StixBundle>,
+    pub pivot: Option<PivotKeywords>,
+    redactor: Option<Redactor>,
+    pub sorter: Option<DetectionSorter>,
+    time_fmt: TimeFormatter,
+}
+
+impl Writers {
+    /// Writers that only collect pivot keywords (for the pivot-keywords-list command).
+    pub fn pivot_only(pivot: PivotKeywords, time_fmt: TimeFormatter) -> Self {
+        Writers {
+            csv: None,
+            json: None,
+            jsonl: None,
+            std: None,
+            sqlite: None,
+            elastic: None,
+            ocsf: None,
+            timesketch: None,
+            parquet: None,
+            html: None,
+            syslog: None,
+            http: None,
+            stix: None,
+            pivot: Some(pivot),
+            redactor: None,
+            sorter: None,
+            time_fmt,
+        }
+    }
+
+    /// Returns true if detections are sent to syslog or HTTP.
+    pub fn has_sinks(&self) -> bool {
+        self.syslog.is_some() || self.http.is_some()
+    }
+
+    /// Writes out the buffered results without closing the outputs (for --follow).
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(writer) = &mut self.csv {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.json {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.jsonl {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.sqlite {
+            writer.commit().map_err(io::Error::other)?;
+        }
+        if let Some(writer) = &mut self.elastic {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.ocsf {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.timesketch {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.syslog {
+            writer.flush().ok();
+        }
+        if let Some(sink) = &mut self.http {
+            sink.flush();
+        }
+        Ok(())
+    }
+
+    /// Writes out the results and closes the outputs at the end of the scan.
+    /// Syslog and HTTP are flushed by the caller to report the messages that could not be sent.
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(writer) = &mut self.csv {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.json {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.jsonl {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.sqlite {
+            writer.finish().map_err(io::Error::other)?;
+        }
+        if let Some(writer) = &mut self.elastic {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.ocsf {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.timesketch {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.parquet {
+            writer.finish().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outputs a record is written to.
+/// When sorting, each detection is sent to the sinks while scanning and the other outputs are
+/// written from the sorted results. With --aggregate-window, the aggregated detections are
+/// written only to the tables and each detection is written to the other outputs.
+#[derive(Clone, Copy, PartialEq)]
+pub struct RecordTarget {
+    // CSV, JSON, HTML report, SQLite, etc.
+    pub tables: bool,
+    // STIX and pivot keywords
+    pub reports: bool,
+    // Syslog and HTTP
+    pub sinks: bool,
+}
+
+impl RecordTarget {
+    pub const ALL: Self = RecordTarget {
+        tables: true,
+        reports: true,
+        sinks: true,
+    };
+    pub const TABLES: Self = RecordTarget {
+        tables: true,
+        reports: false,
+        sinks: false,
+    };
+    pub const REPORTS: Self = RecordTarget {
+        tables: false,
+        reports: true,
+        sinks: false,
+    };
+    pub const SINKS: Self = RecordTarget {
+        tables: false,
+        reports: false,
+        sinks: true,
+    };
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -68,19 +295,84 @@ pub fn write_record(
     profile: &[(String, String)],
     event: &Event,
     json: &Value,
-    rule: &Rule,
+    rule: Option<&Rule>,
     wrt: &mut Writers,
     no_color: bool,
     geo: &mut Option<GeoIPSearch>,
     raw_output: bool,
-) {
+    target: RecordTarget,
+) -> io::Result<()> {
     let mut record: Vec<String> = profile
         .iter()
-        .map(|(_k, v)| get_value_from_event(v, event, rule, geo))
+        .map(|(_k, v)| get_value_from_event(v, event, rule, geo, &wrt.time_fmt))
         .collect();
+    let redacted;
+    let json = if let Some(redactor) = &mut wrt.redactor {
+        redactor.redact_record(profile, &mut record);
+        redacted = redactor.redact_json(json);
+        &redacted
+    } else {
+        json
+    };
+
+    if target.tables {
+        write_tables(
+            profile, event, json, rule, &record, wrt, no_color, geo, raw_output,
+        )?;
+    }
+
+    // The following formats are only for detections.
+    let Some(rule) = rule else {
+        return Ok(());
+    };
+
+    // HTTP出力
+    if let Some(sink) = wrt.http.as_mut().filter(|_| target.sinks) {
+        let doc: BTreeMap<&str, &str> = profile
+            .iter()
+            .zip(&record)
+            .map(|((k, _), v)| (k.as_str(), v.as_str()))
+            .collect();
+        sink.write(serde_json::to_value(doc).unwrap_or_default(), json);
+    }
+
+    // Syslog出力
+    if let Some(writer) = wrt.syslog.as_mut().filter(|_| target.sinks) {
+        writer.write(json, rule);
+    }
+
+    // STIX出力
+    if let Some(bundle) = wrt.stix.as_mut().filter(|_| target.reports) {
+        bundle.add(json, rule);
+    }
+
+    // ピボットキーワード
+    if let Some(pivot) = wrt.pivot.as_mut().filter(|_| target.reports) {
+        pivot.add(json, rule);
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_tables(
+    profile: &[(String, String)],
+    event: &Event,
+    json: &Value,
+    rule: Option<&Rule>,
+    record: &[String],
+    wrt: &mut Writers,
+    no_color: bool,
+    geo: &mut Option<GeoIPSearch>,
+    raw_output: bool,
+) -> io::Result<()> {
+    // HTMLレポート
+    if let (Some(report), Some(_)) = (&mut wrt.html, rule) {
+        report.add(record);
+    }
 
     // 標準出力
     if let Some(writer) = &mut wrt.std {
+        let mut record = record.to_vec();
         let level_index = profile.iter().position(|(k, _)| k == "Level");
         let level = if let Some(index) = level_index {
             let org = record[index].to_lowercase();
@@ -123,7 +415,7 @@ pub fn write_record(
 
     // CSV出力
     if let Some(writer) = &mut wrt.csv {
-        writer.write_record(&record).unwrap();
+        writer.write_record(record)?;
     }
 
     // JSON出力
@@ -136,25 +428,25 @@ pub fn write_record(
                 .cloned()
                 .collect();
             for (k, v) in sigma_profile {
-                let value = get_value_from_event(&v, event, rule, geo);
+                let value = get_value_from_event(&v, event, rule, geo, &wrt.time_fmt);
                 json_record[k] = Value::String(value.to_string());
             }
             let rec = serde_json::to_string_pretty(&json_record);
             if let Ok(json_string) = rec {
-                writer.write_all(json_string.as_bytes()).unwrap();
-                writer.write_all(b"\n").unwrap();
+                writer.write_all(json_string.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+        } else {
+            let json_record: BTreeMap<&str, &str> = profile
+                .iter()
+                .zip(record)
+                .map(|((k, _), v)| (k.as_str(), v.as_str()))
+                .collect();
+            let rec = serde_json::to_string_pretty(&json_record);
+            if let Ok(json_string) = rec {
+                writer.write_all(json_string.as_bytes())?;
+                writer.write_all(b"\n")?;
             }
-            return;
-        }
-        let mut json_record: BTreeMap<String, String> = BTreeMap::new();
-        for (k, v) in profile {
-            let value = get_value_from_event(v, event, rule, geo);
-            json_record.insert(k.clone(), value.to_string());
-        }
-        let rec = serde_json::to_string_pretty(&json_record);
-        if let Ok(json_string) = rec {
-            writer.write_all(json_string.as_bytes()).unwrap();
-            writer.write_all(b"\n").unwrap();
         }
     }
 
@@ -168,26 +460,54 @@ pub fn write_record(
                 .cloned()
                 .collect();
             for (k, v) in sigma_profile {
-                let value = get_value_from_event(&v, event, rule, geo);
+                let value = get_value_from_event(&v, event, rule, geo, &wrt.time_fmt);
                 json_record[k] = Value::String(value.to_string());
             }
             let rec = serde_json::to_string(&json_record);
             if let Ok(json_string) = rec {
-                writer.write_all(json_string.as_bytes()).unwrap();
-                writer.write_all(b"\n").unwrap();
+                writer.write_all(json_string.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+        } else {
+            let json_record: BTreeMap<&str, &str> = profile
+                .iter()
+                .zip(record)
+                .map(|((k, _), v)| (k.as_str(), v.as_str()))
+                .collect();
+            if let Ok(json_string) = serde_json::to_string(&json_record) {
+                writer.write_all(json_string.as_bytes())?;
+                writer.write_all(b"\n")?;
             }
-            return;
-        }
-        let mut json_record: BTreeMap<String, String> = BTreeMap::new();
-        for (k, v) in profile {
-            let value = get_value_from_event(v, event, rule, geo);
-            json_record.insert(k.clone(), value.to_string());
-        }
-        if let Ok(json_string) = serde_json::to_string(&json_record) {
-            writer.write_all(json_string.as_bytes()).unwrap();
-            writer.write_all(b"\n").unwrap();
         }
     }
+
+    // Timesketch出力
+    if let Some(writer) = &mut wrt.timesketch {
+        writer.write(profile, record, json, rule)?;
+    }
+
+    // Parquet出力
+    if let Some(writer) = &mut wrt.parquet {
+        writer.write(record, json).map_err(io::Error::other)?;
+    }
+
+    // SQLite出力
+    if let Some(writer) = &mut wrt.sqlite {
+        writer
+            .insert(record, json, rule)
+            .map_err(io::Error::other)?;
+    }
+
+    // Elasticsearch Bulk出力
+    if let Some(writer) = &mut wrt.elastic {
+        writer.write(profile, record, json, rule, raw_output)?;
+    }
+
+    // OCSF出力
+    if let Some(writer) = &mut wrt.ocsf {
+        writer.write(json, rule, raw_output)?;
+    }
+    Ok(())
 }
 
 fn abbreviate_level(level: &str) -> &str {
@@ -199,7 +519,7 @@ fn abbreviate_level(level: &str) -> &str {
     }
 }
 
-pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
+pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) -> Status {
     let no_color = common_opt.no_color;
     let mut geo_search = None;
     if let Some(path) = options.geo_ip.as_ref() {
@@ -212,20 +532,121 @@ pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
                 "Could not find the appropriate MaxMind GeoIP .mmdb database files.\n",
                 true,
             );
-            return;
+            return Status::Error;
+        }
+    }
+    let time_fmt = match TimeFormatter::new(&options.time_format_opt) {
+        Ok(time_fmt) => time_fmt,
+        Err(e) => {
+            p(Red.rdg(no_color), &e, true);
+            return Status::Error;
+        }
+    };
+    let mut http_sink = None;
+    if options.http_opt.http_url.is_some() {
+        match HttpSink::new(&options.http_opt) {
+            Ok(sink) => http_sink = Some(sink),
+            Err(e) => {
+                p(Red.rdg(no_color), &e, true);
+                return Status::Error;
+            }
+        }
+    }
+    let mut redactor = None;
+    if options.redact_opt.redact {
+        match Redactor::new(&options.redact_opt) {
+            Ok(r) => redactor = Some(r),
+            Err(e) => {
+                p(Red.rdg(no_color), &e, true);
+                return Status::Error;
+            }
+        }
+    }
+    let mut state = None;
+    if let Some(path) = &options.state {
+        match ScanState::load(path, &filter_options(&options.input_opt)) {
+            Ok(s) => state = Some(s),
+            Err(e) => {
+                p(Red.rdg(no_color), &e, true);
+                return Status::Error;
+            }
+        }
+    }
+    if options.follow && state.is_none() {
+        // the files of the initial scan are not scanned again while following
+        state = Some(ScanState::default());
+    }
+    let mut profile = load_profile("config/default_profile.yaml", &geo_search);
+    let mut aggregator = None;
+    if let Some(window) = options.aggregate_window.as_deref().and_then(parse_offset) {
+        aggregator = Some(DetectionAggregator::new(
+            window,
+            options.aggregate_keys.clone(),
+        ));
+        let index = profile
+            .iter()
+            .position(|(k, _)| k == "Timestamp")
+            .map_or(0, |i| i + 1);
+        let columns = [
+            ("LastTimestamp", AGGREGATION_LAST_TIME),
+            ("Count", AGGREGATION_COUNT),
+        ];
+        for (i, (k, v)) in columns.into_iter().enumerate() {
+            profile.insert(index + i, (k.to_string(), format!(".{}", v)));
         }
     }
-    let profile = load_profile("config/default_profile.yaml", &geo_search);
-    let rules: Vec<Rule> = rules::load_rules_from_dir(&options.rules);
+    let (rules, rule_paths): (Vec<Rule>, Vec<PathBuf>) =
+        rules::load_rules_with_path(&options.rules)
+            .into_iter()
+            .unzip();
     if rules.is_empty() {
         p(
             Red.rdg(no_color),
             "Suzaku could not load any rules. Please download the rules with the update-rules command.\n",
             true,
         );
-        return;
+        return Status::Error;
     }
+    let rule_paths: HashMap<String, PathBuf> =
+        rules.iter().map(stix::rule_key).zip(rule_paths).collect();
     let rules = rules::filter_rules_by_level(&rules, &options.min_level);
+    let mut checkpoint = None;
+    if let (true, Some(output_path)) = (options.checkpoint, &options.output) {
+        let loaded = match options.resume {
+            true => Checkpoint::load(output_path),
+            false => Checkpoint::new(output_path),
+        };
+        match loaded {
+            Ok(c) => checkpoint = Some(c),
+            Err(e) => {
+                p(Red.rdg(no_color), &e, true);
+                return Status::Error;
+            }
+        }
+    }
+
+    let mut syslog_writer = None;
+    if let Some(target) = &options.syslog {
+        let format = SyslogFormat::from_name(&options.syslog_format);
+        // the syslog file is truncated to the checkpoint when resuming, like the CSV/JSON files
+        let writer = match (SyslogTarget::parse(target), checkpoint.as_mut()) {
+            (SyslogTarget::File(path), Some(c)) => c
+                .open_output(&path)
+                .map(|file| SyslogWriter::from_file(file, format)),
+            (target, _) => SyslogWriter::new(&target, format),
+        };
+        match writer {
+            Ok(writer) => syslog_writer = Some(writer),
+            Err(e) => {
+                p(
+                    Red.rdg(no_color),
+                    &format!("Could not open the syslog target {}: {}", target, e),
+                    true,
+                );
+                return Status::Error;
+            }
+        }
+    }
 
     p(Green.rdg(no_color), "Total detection rules: ", false);
     p(None, rules.len().to_string().as_str(), true);
@@ -234,40 +655,158 @@ pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
     let mut csv_writer = None;
     let mut json_writer = None;
     let mut jsonl_writer = None;
+    let mut sqlite_writer = None;
+    let mut elastic_writer = None;
+    let mut ocsf_writer = None;
+    let mut timesketch_writer = None;
+    let mut parquet_writer = None;
     let mut output_pathes = vec![];
+    let mut write_csv_header = true;
 
     if let Some(output_path) = &options.output {
         let output_type = OutputType::from_u8(options.output_type).unwrap_or(OutputType::Csv);
         match output_type {
             OutputType::Csv | OutputType::CsvAndJson | OutputType::CsvAndJsonl => {
-                let mut csv_path = output_path.clone();
-                if csv_path.extension().and_then(|ext| ext.to_str()) != Some("csv") {
-                    csv_path.set_extension("csv");
-                }
+                let csv_path = with_extension(output_path, "csv");
                 output_pathes.push(csv_path.clone());
-                csv_writer = Some(get_writer(&Some(csv_path)));
+                // the header is already in the file when appending or resuming
+                write_csv_header = (!options.append
+                    || csv_path.metadata().ok().is_none_or(|m| m.len() == 0))
+                    && checkpoint.as_ref().is_none_or(|c| c.offset(&csv_path) == 0);
+                let file = match checkpoint.as_mut() {
+                    Some(c) => c.open_output(&csv_path),
+                    None => open_output_file(&csv_path, options.append),
+                };
+                let file = match file {
+                    Ok(file) => file,
+                    Err(e) => {
+                        let msg = format!("Failed to create {}: {}", csv_path.display(), e);
+                        p(Red.rdg(no_color), &msg, true);
+                        return Status::Error;
+                    }
+                };
+                csv_writer = Some(Writer::from_writer(Box::new(file) as Box<dyn Write>));
             }
             _ => {}
         }
         match output_type {
             OutputType::Json | OutputType::CsvAndJson => {
-                let mut json_path = output_path.clone();
-                if json_path.extension().and_then(|ext| ext.to_str()) != Some("json") {
-                    json_path.set_extension("json");
-                }
+                let json_path = with_extension(output_path, "json");
                 output_pathes.push(json_path.clone());
-                json_writer = Some(get_json_writer(&Some(json_path)));
+                let file = match checkpoint.as_mut() {
+                    Some(c) => c.open_output(&json_path),
+                    None => File::create(&json_path),
+                };
+                let file = match file {
+                    Ok(file) => file,
+                    Err(e) => {
+                        let msg = format!("Failed to create {}: {}", json_path.display(), e);
+                        p(Red.rdg(no_color), &msg, true);
+                        return Status::Error;
+                    }
+                };
+                json_writer = Some(BufWriter::new(Box::new(file) as Box<dyn Write>));
             }
             OutputType::Jsonl | OutputType::CsvAndJsonl => {
-                let mut jsonl_path = output_path.clone();
-                if jsonl_path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
-                    jsonl_path.set_extension("jsonl");
-                }
+                let jsonl_path = with_extension(output_path, "jsonl");
                 output_pathes.push(jsonl_path.clone());
-                jsonl_writer = Some(get_json_writer(&Some(jsonl_path)));
+                let file = match checkpoint.as_mut() {
+                    Some(c) => c.open_output(&jsonl_path),
+                    None => open_output_file(&jsonl_path, options.append),
+                };
+                let file = match file {
+                    Ok(file) => file,
+                    Err(e) => {
+                        let msg = format!("Failed to create {}: {}", jsonl_path.display(), e);
+                        p(Red.rdg(no_color), &msg, true);
+                        return Status::Error;
+                    }
+                };
+                jsonl_writer = Some(BufWriter::new(Box::new(file) as Box<dyn Write>));
             }
             _ => {}
         }
+        if let OutputType::Sqlite = output_type {
+            let db_path = with_extension(output_path, "db");
+            output_pathes.push(db_path.clone());
+            let writer = SqliteWriter::new(&db_path, &profile)
+                .and_then(|mut w| w.insert_rules(&rules).map(|_| w));
+            match writer {
+                Ok(writer) => sqlite_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", db_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+        }
+        if let OutputType::Elastic = output_type {
+            let ndjson_path = with_extension(output_path, "ndjson");
+            let template_path = elastic::index_template_path(&ndjson_path);
+            if let Err(e) = elastic::write_index_template(&template_path) {
+                let msg = format!("Failed to create {}: {}", template_path.display(), e);
+                p(Red.rdg(no_color), &msg, true);
+                return Status::Error;
+            }
+            match ElasticWriter::new(&ndjson_path) {
+                Ok(writer) => elastic_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", ndjson_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+            output_pathes.push(ndjson_path);
+            output_pathes.push(template_path);
+        }
+        if let OutputType::Ocsf = output_type {
+            let ocsf_path = with_extension(output_path, "jsonl");
+            match OcsfWriter::new(&ocsf_path) {
+                Ok(writer) => ocsf_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", ocsf_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+            output_pathes.push(ocsf_path);
+        }
+        if let OutputType::TimesketchCsv = output_type {
+            let csv_path = with_extension(output_path, "csv");
+            match TimesketchWriter::csv(&csv_path, &profile) {
+                Ok(writer) => timesketch_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", csv_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+            output_pathes.push(csv_path);
+        }
+        if let OutputType::TimesketchJsonl = output_type {
+            let jsonl_path = with_extension(output_path, "jsonl");
+            match TimesketchWriter::jsonl(&jsonl_path) {
+                Ok(writer) => timesketch_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", jsonl_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+            output_pathes.push(jsonl_path);
+        }
+        if let OutputType::Parquet = output_type {
+            let parquet_path = with_extension(output_path, "parquet");
+            match ParquetWriter::new(&parquet_path, &profile) {
+                Ok(writer) => parquet_writer = Some(writer),
+                Err(e) => {
+                    let msg = format!("Failed to create {}: {}", parquet_path.display(), e);
+                    p(Red.rdg(no_color), &msg, true);
+                    return Status::Error;
+                }
+            }
+            output_pathes.push(parquet_path);
+        }
     } else {
         let disp_wtr = BufferWriter::stdout(ColorChoice::Always);
         let mut disp_wtr_buf = disp_wtr.buffer();
@@ -281,32 +820,144 @@ pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
         writeln!(buf, "{}", csv_header.join(" · ")).ok();
     }
 
-    if let Some(ref mut writer) = csv_writer {
+    if let Some(writer) = csv_writer.as_mut().filter(|_| write_csv_header) {
         let csv_header: Vec<&str> = profile.iter().map(|(k, _v)| k.as_str()).collect();
-        writer.write_record(&csv_header).unwrap();
+        if let Err(e) = writer.write_record(&csv_header) {
+            let msg = format!("Failed to write the CSV header: {}", e);
+            p(Red.rdg(no_color), &msg, true);
+            return Status::Error;
+        }
     }
     let mut wrt = Writers {
         csv: csv_writer,
         json: json_writer,
         jsonl: jsonl_writer,
         std: std_writer,
+        sqlite: sqlite_writer,
+        elastic: elastic_writer,
+        ocsf: ocsf_writer,
+        timesketch: timesketch_writer,
+        parquet: parquet_writer,
+        html: options.html_report.as_ref().map(|_| HtmlReport::default()),
+        syslog: syslog_writer,
+        http: http_sink,
+        stix: options.stix.as_ref().map(|_| StixBundle::new(rule_paths)),
+        pivot: options.pivot_keywords.as_ref().map(|_| {
+            PivotKeywords::new(
+                pivot::load_config(pivot::PIVOT_CONFIG_PATH),
+                &options.pivot_min_level,
+            )
+        }),
+        redactor,
+        sorter: (!options.no_sort).then(|| {
+            let sorter = DetectionSorter::new(SORT_MEMORY_BUDGET);
+            match &checkpoint {
+                Some(c) => sorter.with_run_dir(c.dir(), c.runs.clone()),
+                None => sorter,
+            }
+        }),
+        time_fmt,
     };
 
-    let mut summary = DetectionSummary::default();
-    if let Some(d) = &options.input_opt.directory {
-        scan_directory(
-            d,
-            options,
-            &rules,
-            &mut summary,
-            &profile,
-            &mut wrt,
-            common_opt,
-            &mut geo_search,
+    let mut summary = checkpoint
+        .as_mut()
+        .map(Checkpoint::take_summary)
+        .unwrap_or_default();
+    let dedup = match scan_input(
+        options,
+        &rules,
+        &mut summary,
+        &profile,
+        &mut wrt,
+        common_opt,
+        &mut geo_search,
+        state.as_mut(),
+        checkpoint.as_mut(),
+    ) {
+        Ok(dedup) => dedup,
+        Err(e) => {
+            p(Red.rdg(no_color), &e.to_string(), true);
+            return Status::Error;
+        }
+    };
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|c| c.interrupted) {
+        println!();
+        let msg = format!(
+            "The scan was interrupted. The progress was saved to {}.",
+            checkpoint.dir().display()
         );
-    } else if let Some(f) = &options.input_opt.filepath {
-        scan_file(
-            f,
+        p(Orange.rdg(no_color), &msg, true);
+        p(
+            Orange.rdg(no_color),
+            "Please run the same command with --resume to continue the scan.",
+            true,
+        );
+        return Status::Error;
+    }
+    if let Some(state) = state.as_ref().filter(|_| options.state.is_some()) {
+        p(Green.rdg(no_color), "Skipped unchanged log files: ", false);
+        p(
+            None,
+            &state.skipped_files.to_formatted_string(&Locale::en),
+            true,
+        );
+    }
+    if let Some(sorter) = wrt.sorter.take() {
+        let mut write_sorted = |rule_index: Option<usize>, event: &Value, target| {
+            let Ok(json_event) = event_from_json(event.to_string().as_str()) else {
+                return Ok(());
+            };
+            write_record(
+                &profile,
+                &json_event,
+                event,
+                rule_index.map(|i| rules[i]),
+                &mut wrt,
+                no_color,
+                &mut geo_search,
+                options.raw_output,
+                target,
+            )
+        };
+        // the sinks were already sent the detections while scanning
+        let sorted_target = RecordTarget {
+            sinks: false,
+            ..RecordTarget::ALL
+        };
+        let sorted = sorter.into_sorted_iter().and_then(|sorted| {
+            for detection in sorted {
+                let (rule_index, event) = detection?;
+                match (&mut aggregator, rule_index) {
+                    (Some(agg), Some(i)) => {
+                        write_sorted(Some(i), &event, RecordTarget::REPORTS)?;
+                        for (i, event) in agg.push(i, event) {
+                            write_sorted(Some(i), &event, RecordTarget::TABLES)?;
+                        }
+                    }
+                    _ => write_sorted(rule_index, &event, sorted_target)?,
+                }
+            }
+            if let Some(agg) = aggregator {
+                for (i, event) in agg.finish() {
+                    write_sorted(Some(i), &event, RecordTarget::TABLES)?;
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = sorted {
+            let msg = format!("Failed to write the sorted results: {}", e);
+            p(Red.rdg(no_color), &msg, true);
+            return Status::Error;
+        }
+    }
+    let mut status = Status::Clean;
+    if let (true, Some(state)) = (options.follow, state.as_mut()) {
+        if let Err(e) = wrt.flush() {
+            let msg = format!("Failed to write the results: {}", e);
+            p(Red.rdg(no_color), &msg, true);
+            return Status::Error;
+        }
+        if let Err(e) = follow_input(
             options,
             &rules,
             &mut summary,
@@ -314,16 +965,53 @@ pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
             &mut wrt,
             common_opt,
             &mut geo_search,
-        );
+            state,
+            dedup,
+        ) {
+            p(Red.rdg(no_color), &e.to_string(), true);
+            status = Status::Error;
+        }
     }
-    if let Some(ref mut writer) = wrt.csv {
-        writer.flush().unwrap();
+    if let Err(e) = wrt.finish() {
+        let msg = format!("Failed to write the results: {}", e);
+        p(Red.rdg(no_color), &msg, true);
+        return Status::Error;
     }
-    if let Some(ref mut writer) = wrt.json {
-        writer.flush().unwrap();
+    if let Some(ref mut writer) = wrt.syslog {
+        writer.flush().ok();
+        if writer.failed > 0 {
+            let msg = format!("{} syslog messages could not be sent.", writer.failed);
+            p(Red.rdg(no_color), &msg, true);
+        }
+        if let Some(SyslogTarget::File(path)) = options.syslog.as_deref().map(SyslogTarget::parse) {
+            output_pathes.push(path);
+        }
+    }
+    if let Some(ref mut sink) = wrt.http {
+        sink.flush();
+        p(Green.rdg(no_color), "Detections sent via HTTP: ", false);
+        p(None, &sink.sent.to_formatted_string(&Locale::en), true);
+        if sink.spooled > 0 {
+            let msg = format!(
+                "{} detections could not be sent and were saved to {}",
+                sink.spooled.to_formatted_string(&Locale::en),
+                sink.spool_path().display()
+            );
+            p(Red.rdg(no_color), &msg, true);
+            output_pathes.push(sink.spool_path().clone());
+        }
+        if sink.dropped > 0 {
+            let msg = format!(
+                "{} detections could not be sent nor saved to {}",
+                sink.dropped.to_formatted_string(&Locale::en),
+                sink.spool_path().display()
+            );
+            p(Red.rdg(no_color), &msg, true);
+            status = Status::Error;
+        }
     }
-    if let Some(ref mut writer) = wrt.jsonl {
-        writer.flush().unwrap();
+    if let Some(checkpoint) = &checkpoint {
+        checkpoint.remove();
     }
     println!();
     let terminal_width = match terminal_size() {
@@ -354,15 +1042,60 @@ pub fn aws_detect(options: &AwsCtTimelineOptions, common_opt: &CommonOptions) {
     print_detected_rule_authors(&authors_count, table_column_num, no_color);
 
     if !options.no_summary {
-        print_summary(&summary, no_color);
+        print_summary(&summary, no_color, &wrt.time_fmt);
+    }
+
+    // the other outputs are still written when one of them fails
+    let mut write_output = |path: &Path, result: io::Result<()>| match result {
+        Ok(()) => output_pathes.push(path.to_path_buf()),
+        Err(e) => {
+            let msg = format!("Failed to write {}: {}", path.display(), e);
+            p(Red.rdg(no_color), &msg, true);
+            status = Status::Error;
+        }
+    };
+    if let (Some(report), Some(path)) = (&wrt.html, &options.html_report) {
+        write_output(path, report.write(path, &profile, &summary, &wrt.time_fmt));
+    }
+    if let (Some(bundle), Some(path)) = (&wrt.stix, &options.stix) {
+        write_output(path, bundle.write(path));
+    }
+    if let Some(path) = &options.summary_output {
+        write_output(path, write_summary_json(path, &summary));
+    }
+    if let (Some(redactor), Some(path)) = (&wrt.redactor, &options.redact_opt.redact_map) {
+        write_output(path, redactor.write_map(path));
+    }
+    if let (Some(state), Some(path)) = (&state, &options.state) {
+        write_output(path, state.save(path));
+    }
+    if let (Some(pivot), Some(path)) = (&wrt.pivot, &options.pivot_keywords) {
+        pivot.print_table(no_color);
+        match pivot.write(path) {
+            Ok(paths) => output_pathes.extend(paths),
+            Err(e) => {
+                let msg = format!("Failed to write {}: {}", path.display(), e);
+                p(Red.rdg(no_color), &msg, true);
+                status = Status::Error;
+            }
+        }
     }
 
     if !output_pathes.is_empty() {
         output_path_info(no_color, &output_pathes);
     }
+    if let Some(level) = &options.fail_on_level {
+        if status == Status::Clean && summary.has_hits_at_or_above(level) {
+            let msg = format!("Detections at or above the {} level were found.", level);
+            p(Red.rdg(no_color), &msg, true);
+            println!();
+            status = Status::Detected;
+        }
+    }
+    status
 }
 
-fn print_summary(sum: &DetectionSummary, no_color: bool) {
+fn print_summary(sum: &DetectionSummary, no_color: bool, time_fmt: &TimeFormatter) {
     let levels = if no_color {
         vec![
             ("critical", White),
@@ -382,9 +1115,12 @@ fn print_summary(sum: &DetectionSummary, no_color: bool) {
     };
     print_summary_header(sum, no_color);
     print_summary_levels(sum, &levels);
-    print_summary_event_times(sum);
+    print_summary_event_times(sum, time_fmt);
     print_summary_dates_with_hits(sum, &levels);
     print_summary_table(sum, &levels);
+    if !sum.insights.is_empty() {
+        sum.insights.print_table(no_color, time_fmt);
+    }
 }
 
 fn print_summary_header(sum: &DetectionSummary, no_color: bool) {
@@ -404,12 +1140,31 @@ fn print_summary_header(sum: &DetectionSummary, no_color: bool) {
         &format!(
             "Data reduction: {} events ({:.2}%)",
             (sum.total_events - sum.event_with_hits).to_formatted_string(&Locale::en),
-            (sum.total_events - sum.event_with_hits) as f64 * 100.0 / sum.total_events as f64
+            (sum.total_events - sum.event_with_hits) as f64 * 100.0
+                / sum.total_events.max(1) as f64
         ),
         false,
     );
     p(None, ")", false);
     println!();
+    // イベントカテゴリ別の件数
+    let categories: Vec<String> = sum
+        .category_events
+        .iter()
+        .map(|(category, events)| {
+            let hits = sum.category_hits.get(category).copied().unwrap_or(0);
+            format!(
+                "{}: {} (hits: {})",
+                category,
+                events.to_formatted_string(&Locale::en),
+                hits.to_formatted_string(&Locale::en)
+            )
+        })
+        .collect();
+    if !categories.is_empty() {
+        p(Green.rdg(no_color), "Events by category: ", false);
+        p(None, &categories.join(" / "), true);
+    }
 }
 
 fn print_summary_levels(sum: &DetectionSummary, levels: &Vec<(&str, SuzakuColor)>) {
@@ -434,14 +1189,14 @@ fn print_summary_levels(sum: &DetectionSummary, levels: &Vec<(&str, SuzakuColor)
     println!();
 }
 
-fn print_summary_event_times(sum: &DetectionSummary) {
+fn print_summary_event_times(sum: &DetectionSummary, time_fmt: &TimeFormatter) {
     if let Some(first_event_time) = sum.first_event_time {
         p(None, "First event time: ", false);
-        p(None, &first_event_time.to_string(), true);
+        p(None, &time_fmt.format(&first_event_time), true);
     }
     if let Some(last_event_time) = sum.last_event_time {
         p(None, "Last event time: ", false);
-        p(None, &last_event_time.to_string(), true);
+        p(None, &time_fmt.format(&last_event_time), true);
     }
     println!();
 }
@@ -551,11 +1306,11 @@ fn print_detected_rule_authors(
     no_color: bool,
 ) {
     let mut sorted_authors: Vec<(&String, &i128)> = rule_author_counter.iter().collect();
-    sorted_authors.sort_by(|a, b| (-a.1).cmp(&(-b.1)));
+    sorted_authors.sort_by_key(|a| -a.1);
     let authors_num = sorted_authors.len();
     let div = if authors_num <= table_column_num {
         1
-    } else if authors_num % 4 != 0 {
+    } else if !authors_num.is_multiple_of(4) {
         authors_num / table_column_num + 1
     } else {
         authors_num / table_column_num
@@ -670,8 +1425,9 @@ fn load_profile(file_path: &str, geo_search: &Option<GeoIPSearch>) -> Vec<(Strin
 fn get_value_from_event(
     key: &str,
     event: &Event,
-    rule: &Rule,
+    rule: Option<&Rule>,
     geo_ip: &mut Option<GeoIPSearch>,
+    time_fmt: &TimeFormatter,
 ) -> String {
     if let Some(geo) = geo_ip {
         if let Some(ip) = event.get("sourceIPAddress") {
@@ -692,8 +1448,8 @@ fn get_value_from_event(
     if key.starts_with(".") {
         let key = key.strip_prefix(".").unwrap();
         if let Some(value) = event.get(key) {
-            if key == "eventTime" {
-                value.value_to_string().replace("T", " ").replace("Z", "")
+            if key == "eventTime" || key == AGGREGATION_LAST_TIME {
+                time_fmt.format_str(&value.value_to_string())
             } else {
                 value.value_to_string()
             }
@@ -702,32 +1458,70 @@ fn get_value_from_event(
         }
     } else if key.starts_with("sigma.") {
         let key = key.replace("sigma.", "");
-        if key == "title" {
-            rule.title.to_string()
-        } else if key == "id" && rule.id.is_some() {
-            rule.id.as_ref().unwrap().to_string()
-        } else if key == "status" && rule.status.is_some() {
-            format!("{:?}", rule.status.as_ref().unwrap()).to_lowercase()
-        } else if key == "author" && rule.author.is_some() {
-            rule.author.as_ref().unwrap().to_string()
-        } else if key == "description" && rule.description.is_some() {
-            rule.description.as_ref().unwrap().to_string()
-        } else if key == "references" && rule.references.is_some() {
-            format!("{:?}", rule.references.as_ref().unwrap())
-        } else if key == "date" && rule.date.is_some() {
-            rule.date.as_ref().unwrap().to_string()
-        } else if key == "modified" && rule.modified.is_some() {
-            rule.modified.as_ref().unwrap().to_string()
-        } else if key == "tags" && rule.tags.is_some() {
-            format!("{:?}", rule.tags.as_ref().unwrap())
-        } else if key == "falsepositives" && rule.falsepositives.is_some() {
-            format!("{:?}", rule.falsepositives.as_ref().unwrap())
-        } else if key == "level" {
-            format!("{:?}", rule.level.as_ref().unwrap()).to_lowercase()
-        } else {
-            "-".to_string()
+        match rule {
+            Some(rule) => get_rule_value(&key, rule).unwrap_or_else(|| "-".to_string()),
+            // events without detections have no rule information
+            None => "".to_string(),
         }
     } else {
         "-".to_string()
     }
 }
+
+pub fn get_rule_value(key: &str, rule: &Rule) -> Option<String> {
+    match key {
+        "title" => Some(rule.title.to_string()),
+        "id" => rule.id.clone(),
+        "status" => rule
+            .status
+            .as_ref()
+            .map(|s| format!("{:?}", s).to_lowercase()),
+        "author" => rule.author.clone(),
+        "description" => rule.description.clone(),
+        "references" => rule.references.as_ref().map(|r| format!("{:?}", r)),
+        "date" => rule.date.clone(),
+        "modified" => rule.modified.clone(),
+        "tags" => rule.tags.as_ref().map(|t| format!("{:?}", t)),
+        "falsepositives" => rule.falsepositives.as_ref().map(|f| format!("{:?}", f)),
+        "level" => rule
+            .level
+            .as_ref()
+            .map(|l| format!("{:?}", l).to_lowercase()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary_with_hits(levels: &[(&str, usize)]) -> DetectionSummary {
+        let mut summary = DetectionSummary::default();
+        for (level, hits) in levels {
+            let rules = summary
+                .level_with_hits
+                .entry(level.to_string())
+                .or_default();
+            if *hits > 0 {
+                rules.insert("CloudTrail Deleted".to_string(), *hits);
+            }
+        }
+        summary
+    }
+
+    #[test]
+    fn test_has_hits_at_or_above() {
+        let summary = summary_with_hits(&[("medium", 2)]);
+        assert!(summary.has_hits_at_or_above("low"));
+        assert!(summary.has_hits_at_or_above("medium"));
+        assert!(summary.has_hits_at_or_above("med"));
+        assert!(!summary.has_hits_at_or_above("high"));
+    }
+
+    #[test]
+    fn test_has_hits_at_or_above_ignores_levels_without_hits() {
+        let summary = summary_with_hits(&[("critical", 0), ("low", 1)]);
+        assert!(!summary.has_hits_at_or_above("high"));
+        assert!(!DetectionSummary::default().has_hits_at_or_above("informational"));
+    }
+}
@@ -1,5 +1,7 @@
-use crate::core::scan::{get_content, load_json_from_file, process_events_from_dir};
-use crate::core::util::{get_writer, output_path_info, p};
+use crate::cmd::Status;
+use crate::core::color::SuzakuColor::Red;
+use crate::core::scan::process_events_from_input;
+use crate::core::util::{output_path_info, p};
 use crate::option::cli::InputOption;
 use crate::option::timefiler::filter_by_time;
 use comfy_table::{Cell, CellAlignment, Table};
@@ -7,21 +9,34 @@ use csv::Writer;
 use serde_json::Value;
 use sigma_rust::{Event, event_from_json};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io;
 use std::io::Write;
 use std::path::PathBuf;
 use termcolor::Color;
 
-pub fn aws_metrics(input_opt: &InputOption, field: &str, output: &Option<PathBuf>, no_color: bool) {
-    let directory = &input_opt.directory;
-    let file = &input_opt.filepath;
-    let mut wtr = get_writer(output);
+pub fn aws_metrics(
+    input_opt: &InputOption,
+    field: &str,
+    output: &Option<PathBuf>,
+    no_color: bool,
+) -> Status {
+    let file: Box<dyn Write> = match output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                let msg = format!("Failed to create {}: {}", path.display(), e);
+                p(Red.rdg(no_color), &msg, true);
+                return Status::Error;
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
+    let wtr = Writer::from_writer(file);
     let csv_header = vec!["EventName", "Percent", "Total"];
-    if output.is_some() {
-        wtr.write_record(&csv_header).unwrap();
-    }
 
     let mut count_map = HashMap::new();
-    let mut stats_func = |json_values: &[Value]| {
+    let stats_func = |json_values: &[Value]| {
         for json_value in json_values {
             if !filter_by_time(&input_opt.time_opt, json_value) {
                 continue;
@@ -30,7 +45,10 @@ pub fn aws_metrics(input_opt: &InputOption, field: &str, output: &Option<PathBuf
                 Ok(event) => event,
                 Err(_) => continue,
             };
-            let value = event.get(field);
+            // Insights events have eventName and eventSource in insightDetails
+            let value = event
+                .get(field)
+                .or_else(|| event.get(&format!("insightDetails.{}", field)));
             if let Some(value) = value {
                 let event_name = value.value_to_string();
                 let count = count_map.entry(event_name).or_insert(0);
@@ -39,17 +57,26 @@ pub fn aws_metrics(input_opt: &InputOption, field: &str, output: &Option<PathBuf
         }
     };
 
-    if let Some(d) = directory {
-        process_events_from_dir(stats_func, d, true, no_color).unwrap();
-        print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
-    } else if let Some(f) = file {
-        let log_contents = get_content(f);
-        let events = load_json_from_file(&log_contents);
-        if let Ok(events) = events {
-            stats_func(&events);
-            print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
-        }
+    if let Err(e) = process_events_from_input(
+        stats_func,
+        input_opt,
+        input_opt.directory.is_some(),
+        no_color,
+        None,
+        None,
+    ) {
+        p(Red.rdg(no_color), &e.to_string(), true);
+        return Status::Error;
+    }
+    if let Err(e) = print_count_map_desc(csv_header, &count_map, wtr, output, no_color) {
+        let msg = match output {
+            Some(path) => format!("Failed to write {}: {}", path.display(), e),
+            None => e.to_string(),
+        };
+        p(Red.rdg(no_color), &msg, true);
+        return Status::Error;
     }
+    Status::Clean
 }
 
 fn print_count_map_desc(
@@ -58,7 +85,10 @@ fn print_count_map_desc(
     mut wrt: Writer<Box<dyn Write>>,
     output: &Option<PathBuf>,
     no_color: bool,
-) {
+) -> csv::Result<()> {
+    if output.is_some() {
+        wrt.write_record(&csv_header)?;
+    }
     let header_cells: Vec<Cell> = csv_header
         .iter()
         .map(|s| Cell::new(s).set_alignment(CellAlignment::Center))
@@ -72,7 +102,7 @@ fn print_count_map_desc(
 
     if total == 0 {
         p(Some(Color::Rgb(255, 0, 0)), "No events found.", true);
-        return;
+        return Ok(());
     }
 
     for (event_name, count) in total_vec {
@@ -83,12 +113,13 @@ fn print_count_map_desc(
         if output.is_none() {
             table.add_row(record.iter().map(Cell::new));
         } else {
-            wrt.write_record(record).unwrap();
+            wrt.write_record(record)?;
         }
     }
-    wrt.flush().ok();
+    wrt.flush()?;
     match output {
         Some(csv) => output_path_info(no_color, [csv.clone()].as_slice()),
         None => println!("{}", table),
     }
+    Ok(())
 }
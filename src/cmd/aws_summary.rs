@@ -1,10 +1,14 @@
+use crate::cmd::Status;
 use crate::core::color::SuzakuColor::Red;
-use crate::core::scan::{get_content, load_json_from_file, process_events_from_dir};
-use crate::core::util::{get_writer, output_path_info, p};
-use crate::option::cli::InputOption;
+use crate::core::redact::Redactor;
+use crate::core::scan::process_events_from_input;
+use crate::core::util::{INSIGHT_CATEGORY, event_category, get_json_str, output_path_info, p};
+use crate::option::cli::{InputOption, RedactOptions, TimeFormatOptions};
 use crate::option::geoip::GeoIPSearch;
 use crate::option::timefiler::filter_by_time;
-use csv::ReaderBuilder;
+use crate::option::timeformat::TimeFormatter;
+use crate::output::insight::InsightSummary;
+use csv::{ReaderBuilder, Writer};
 use itertools::Itertools;
 use num_format::{Locale, ToFormattedString};
 use serde_json::Value;
@@ -114,6 +118,7 @@ impl CTSummary {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn aws_summary(
     input_opt: &InputOption,
     output: &Path,
@@ -121,9 +126,9 @@ pub fn aws_summary(
     include_sts: &bool,
     hide_descriptions: &bool,
     geo_ip: &Option<PathBuf>,
-) {
-    let directory = &input_opt.directory;
-    let file = &input_opt.filepath;
+    time_format_opt: &TimeFormatOptions,
+    redact_opt: &RedactOptions,
+) -> Status {
     let mut geo_search = None;
     if let Some(path) = geo_ip.as_ref() {
         let res = GeoIPSearch::new(path);
@@ -135,15 +140,51 @@ pub fn aws_summary(
                 "Could not find the appropriate MaxMind GeoIP .mmdb database files.\n",
                 true,
             );
-            return;
+            return Status::Error;
+        }
+    }
+    let time_fmt = match TimeFormatter::new(time_format_opt) {
+        Ok(time_fmt) => time_fmt,
+        Err(e) => {
+            p(Red.rdg(no_color), &e, true);
+            return Status::Error;
+        }
+    };
+    let mut redactor = None;
+    if redact_opt.redact {
+        match Redactor::new(redact_opt) {
+            Ok(r) => redactor = Some(r),
+            Err(e) => {
+                p(Red.rdg(no_color), &e, true);
+                return Status::Error;
+            }
         }
     }
     let abused_aws_api_calls = read_abused_aws_api_calls("rules/config/abused_aws_api_calls.csv");
     let mut user_data: HashMap<String, CTSummary> = HashMap::new();
+    let mut insights = InsightSummary::default();
     let mut single_summary_func = |json_value: &Value| {
         if !filter_by_time(&input_opt.time_opt, json_value) {
             return;
         }
+        // Insights events are not made by a user, so they are summarized separately
+        if event_category(json_value) == INSIGHT_CATEGORY {
+            insights.add(json_value);
+            return;
+        }
+        let access_key_id = get_json_str(json_value, "userIdentity.accessKeyId");
+        if !*include_sts && access_key_id.is_some_and(|key| key.starts_with("ASIA")) {
+            return;
+        }
+        // GeoIP info is looked up with the original IP address
+        let original_ip = get_json_str(json_value, "sourceIPAddress").map(str::to_string);
+        let redacted;
+        let json_value = if let Some(redactor) = redactor.as_mut() {
+            redacted = redactor.redact_json(json_value);
+            &redacted
+        } else {
+            json_value
+        };
         let event: Event = match event_from_json(json_value.to_string().as_str()) {
             Ok(event) => event,
             Err(_) => return,
@@ -168,8 +209,8 @@ pub fn aws_summary(
         let source_ipaddress = match event.get("sourceIPAddress") {
             Some(ip) => {
                 let mut ip_str = ip.value_to_string();
-                if let Some(geo) = geo_search.as_mut() {
-                    if let Some(ip) = geo.convert(ip_str.as_str()) {
+                if let (Some(geo), Some(original_ip)) = (geo_search.as_mut(), &original_ip) {
+                    if let Some(ip) = geo.convert(original_ip) {
                         let asn = geo.get_asn(ip);
                         let country = geo.get_country(ip);
                         let city = geo.get_city(ip);
@@ -185,13 +226,7 @@ pub fn aws_summary(
             None => "-".to_string(),
         };
         let user_identity_access_key_id = match event.get("userIdentity.accessKeyId") {
-            Some(access_key_id) => {
-                let key = access_key_id.value_to_string();
-                if !*include_sts && key.starts_with("ASIA") {
-                    return;
-                }
-                key
-            }
+            Some(access_key_id) => access_key_id.value_to_string(),
             None => "-".to_string(),
         };
         let user_agent = match event.get("userAgent") {
@@ -245,54 +280,72 @@ pub fn aws_summary(
             other_api_failed,
         );
     };
-    let mut summary_func = |json_values: &[Value]| {
+    let summary_func = |json_values: &[Value]| {
         for json_value in json_values {
             single_summary_func(json_value);
         }
     };
     let abused_aws_api_values: Vec<String> = abused_aws_api_calls.values().cloned().collect();
-    if let Some(d) = directory {
-        process_events_from_dir(summary_func, d, true, no_color).unwrap();
-        output_summary(
-            &user_data,
-            output,
-            no_color,
-            hide_descriptions,
-            abused_aws_api_values,
-        );
-    } else if let Some(f) = file {
-        let log_contents = get_content(f);
-        let events = load_json_from_file(&log_contents);
-        if let Ok(events) = events {
-            summary_func(&events);
-            output_summary(
-                &user_data,
-                output,
-                no_color,
-                hide_descriptions,
-                abused_aws_api_values,
-            );
-        }
+    if let Err(e) = process_events_from_input(
+        summary_func,
+        input_opt,
+        input_opt.directory.is_some(),
+        no_color,
+        None,
+        None,
+    ) {
+        p(Red.rdg(no_color), &e.to_string(), true);
+        return Status::Error;
+    }
+    if !insights.is_empty() {
+        insights.print_table(no_color, &time_fmt);
     }
-}
-
-fn output_summary(
-    user_data: &HashMap<String, CTSummary>,
-    output: &Path,
-    no_color: bool,
-    hide_descriptions: &bool,
-    abused_aws_api_disc: Vec<String>,
-) {
     if user_data.is_empty() {
         p(Some(Color::Rgb(255, 0, 0)), "No events found.", true);
-        return;
+        return Status::Clean;
+    }
+    let csv_path = summary_csv_path(output);
+    if let Err(e) = output_summary(
+        &user_data,
+        &csv_path,
+        hide_descriptions,
+        abused_aws_api_values,
+        &time_fmt,
+    ) {
+        let msg = format!("Failed to write {}: {}", csv_path.display(), e);
+        p(Red.rdg(no_color), &msg, true);
+        return Status::Error;
     }
+    let mut output_pathes = vec![csv_path];
+    if let (Some(redactor), Some(path)) = (&redactor, &redact_opt.redact_map) {
+        if let Err(e) = redactor.write_map(path) {
+            let msg = format!("Failed to write {}: {}", path.display(), e);
+            p(Red.rdg(no_color), &msg, true);
+            return Status::Error;
+        }
+        output_pathes.push(path.clone());
+    }
+    output_path_info(no_color, &output_pathes);
+    Status::Clean
+}
 
+/// The summary is always written as a CSV file.
+pub fn summary_csv_path(output: &Path) -> PathBuf {
     let mut csv_path = output.to_path_buf();
     if csv_path.extension().and_then(|ext| ext.to_str()) != Some("csv") {
         csv_path.set_extension("csv");
     }
-    let mut csv_wtr = get_writer(&Some(csv_path.clone()));
+    csv_path
+}
+
+fn output_summary(
+    user_data: &HashMap<String, CTSummary>,
+    csv_path: &Path,
+    hide_descriptions: &bool,
+    abused_aws_api_disc: Vec<String>,
+    time_fmt: &TimeFormatter,
+) -> csv::Result<()> {
+    let mut csv_wtr = Writer::from_path(csv_path)?;
     let csv_header = vec![
         "UserARN",
         "NumOfEvents",
@@ -309,10 +362,10 @@ fn output_summary(
         "UserAgents",
     ];
 
-    csv_wtr.write_record(&csv_header).unwrap();
+    csv_wtr.write_record(&csv_header)?;
 
     let mut sorted_user_data: Vec<_> = user_data.iter().collect();
-    sorted_user_data.sort_by(|a, b| b.1.num_of_events.cmp(&a.1.num_of_events));
+    sorted_user_data.sort_by_key(|a| std::cmp::Reverse(a.1.num_of_events));
 
     let fmt_key_total = |msg: &str, map: &HashMap<String, (usize, String, String)>| -> String {
         let total: usize = map.keys().len();
@@ -326,8 +379,8 @@ fn output_summary(
                         "{} - {} ({} ~ {})",
                         v.0.to_formatted_string(&Locale::en),
                         k,
-                        v.1.replace('Z', "").replace('T', " "),
-                        v.2.replace('Z', "").replace('T', " ")
+                        time_fmt.format_str(&v.1),
+                        time_fmt.format_str(&v.2)
                     )
                 }),
         );
@@ -342,16 +395,8 @@ fn output_summary(
 
     for (user_arn, summary) in sorted_user_data.iter() {
         let num_of_events = summary.num_of_events.to_formatted_string(&Locale::en);
-        let first_timestamp = summary
-            .first_timestamp
-            .clone()
-            .replace("T", " ")
-            .replace("Z", "");
-        let last_timestamp = summary
-            .last_timestamp
-            .clone()
-            .replace("T", " ")
-            .replace("Z", "");
+        let first_timestamp = time_fmt.format_str(&summary.first_timestamp);
+        let last_timestamp = time_fmt.format_str(&summary.last_timestamp);
         let aws_regions = fmt_key_total("Total regions", &summary.aws_regions);
         let src_ips = fmt_key_total("Total source IPs", &summary.src_ips);
         let user_types = &summary.user_types;
@@ -388,26 +433,24 @@ fn output_summary(
             abused_fai = abused_fai.replace("-  (2", "(2");
         }
 
-        csv_wtr
-            .write_record(vec![
-                user_arn,
-                &num_of_events,
-                &first_timestamp,
-                &last_timestamp,
-                &abused_suc,
-                &abused_fai,
-                &other_suc,
-                &other_fai,
-                &aws_regions,
-                &src_ips,
-                &user_types,
-                &access_key_ids,
-                &user_agents,
-            ])
-            .unwrap();
+        csv_wtr.write_record(vec![
+            user_arn,
+            &num_of_events,
+            &first_timestamp,
+            &last_timestamp,
+            &abused_suc,
+            &abused_fai,
+            &other_suc,
+            &other_fai,
+            &aws_regions,
+            &src_ips,
+            &user_types,
+            &access_key_ids,
+            &user_agents,
+        ])?;
     }
-    csv_wtr.flush().unwrap();
-    output_path_info(no_color, [csv_path].as_slice());
+    csv_wtr.flush()?;
+    Ok(())
 }
 
 fn read_abused_aws_api_calls(file_path: &str) -> HashMap<String, String> {
@@ -0,0 +1,76 @@
+use crate::cmd::Status;
+use crate::cmd::aws_detect::{DetectionSummary, Writers};
+use crate::core::color::SuzakuColor::{Green, Red};
+use crate::core::rules;
+use crate::core::scan::scan_input;
+use crate::core::util::{output_path_info, p};
+use crate::option::cli::{AwsCtTimelineOptions, CommonOptions, InputOption, TimeFormatOptions};
+use crate::option::timeformat::TimeFormatter;
+use crate::output::pivot;
+use crate::output::pivot::PivotKeywords;
+use sigma_rust::Rule;
+use std::path::PathBuf;
+
+pub fn pivot_keywords_list(
+    rules_path: &PathBuf,
+    input_opt: &InputOption,
+    output: &Option<PathBuf>,
+    min_level: &str,
+    common_opt: &CommonOptions,
+) -> Status {
+    let no_color = common_opt.no_color;
+    let rules: Vec<Rule> = rules::load_rules_from_dir(rules_path);
+    if rules.is_empty() {
+        p(
+            Red.rdg(no_color),
+            "Suzaku could not load any rules. Please download the rules with the update-rules command.\n",
+            true,
+        );
+        return Status::Error;
+    }
+    // detections under the minimum level are not needed for the pivot keywords
+    let rules = rules::filter_rules_by_level(&rules, min_level);
+    p(Green.rdg(no_color), "Total detection rules: ", false);
+    p(None, rules.len().to_string().as_str(), true);
+
+    let options = AwsCtTimelineOptions {
+        rules: rules_path.clone(),
+        input_opt: input_opt.clone(),
+        output: output.clone(),
+        ..Default::default()
+    };
+    let time_fmt = TimeFormatter::new(&TimeFormatOptions::default()).unwrap();
+    let pivot = PivotKeywords::new(pivot::load_config(pivot::PIVOT_CONFIG_PATH), min_level);
+    let mut wrt = Writers::pivot_only(pivot, time_fmt);
+    let mut summary = DetectionSummary::default();
+    if let Err(e) = scan_input(
+        &options,
+        &rules,
+        &mut summary,
+        &[],
+        &mut wrt,
+        common_opt,
+        &mut None,
+        None,
+        None,
+    ) {
+        p(Red.rdg(no_color), &e.to_string(), true);
+        return Status::Error;
+    }
+    println!();
+    let Some(pivot) = wrt.pivot else {
+        return Status::Clean;
+    };
+    pivot.print_table(no_color);
+    if let Some(path) = output {
+        match pivot.write(path) {
+            Ok(paths) => output_path_info(no_color, &paths),
+            Err(e) => {
+                let msg = format!("Failed to write {}: {}", path.display(), e);
+                p(Red.rdg(no_color), &msg, true);
+                return Status::Error;
+            }
+        }
+    }
+    Status::Clean
+}
@@ -1,3 +1,4 @@
+use crate::cmd::Status;
 use crate::core::rules::load_rules_from_dir;
 use crate::core::util::p;
 use git2::{ErrorCode, Repository};
@@ -9,16 +10,17 @@ use std::path::{Path, PathBuf};
 use crate::core::color::SuzakuColor::Orange;
 use termcolor::Color;
 
-pub fn start_update_rules(no_color: bool) {
+pub fn start_update_rules(no_color: bool) -> Status {
     // エラーが出た場合はインターネット接続がそもそもできないなどの問題点もあるためエラー等の出力は行わない
     let latest_version_data = get_latest_suzaku_version().unwrap_or_default();
     let now_version = &format!("v{}", env!("CARGO_PKG_VERSION"));
 
-    match update_rules() {
+    let status = match update_rules() {
         Ok(output) => {
             if output != "You currently have the latest rules." {
                 p(Orange.rdg(no_color), "Rules updated successfully.", true);
             }
+            Status::Clean
         }
         Err(e) => {
             if e.message().is_empty() {
@@ -26,8 +28,9 @@ pub fn start_update_rules(no_color: bool) {
             } else {
                 p(None, &format!("Failed to update rules. {e:?}  "), true);
             }
+            Status::Error
         }
-    }
+    };
 
     let split_now_version = &now_version
         .replace("-dev", "")
@@ -59,6 +62,7 @@ pub fn start_update_rules(no_color: bool) {
         );
     }
     println!();
+    status
 }
 
 /// get latest suzaku version number.
@@ -1,4 +1,10 @@
+pub mod aggregate;
+pub mod checkpoint;
 pub mod color;
+pub mod dedup;
+pub mod redact;
 pub mod rules;
 pub mod scan;
+pub mod sort;
+pub mod state;
 pub mod util;
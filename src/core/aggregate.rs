@@ -0,0 +1,187 @@
+use crate::core::util::get_json_value;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+
+/// The field added to an aggregated detection with its count and first/last event times.
+pub const AGGREGATION_KEY: &str = "suzakuAggregation";
+pub const AGGREGATION_FIRST_TIME: &str = "suzakuAggregation.firstEventTime";
+pub const AGGREGATION_LAST_TIME: &str = "suzakuAggregation.lastEventTime";
+pub const AGGREGATION_COUNT: &str = "suzakuAggregation.count";
+
+struct Group {
+    key: (usize, Vec<String>),
+    event: Value,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+    count: usize,
+}
+
+impl Group {
+    fn into_detection(self) -> (usize, Value) {
+        let mut event = self.event;
+        if let Value::Object(map) = &mut event {
+            map.insert(
+                AGGREGATION_KEY.to_string(),
+                json!({
+                    "count": self.count,
+                    "firstEventTime": self.first.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                    "lastEventTime": self.last.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                }),
+            );
+        }
+        (self.key.0, event)
+    }
+}
+
+/// Collapses detections with the same rule and key fields within a time window into one.
+/// The detections must be pushed in the order of eventTime.
+pub struct DetectionAggregator {
+    window: Duration,
+    keys: Vec<String>,
+    // Open groups in the order of their first event time.
+    groups: BTreeMap<u64, Group>,
+    group_ids: HashMap<(usize, Vec<String>), u64>,
+    next_id: u64,
+}
+
+impl DetectionAggregator {
+    pub fn new(window: Duration, keys: Vec<String>) -> Self {
+        DetectionAggregator {
+            window,
+            keys,
+            groups: BTreeMap::new(),
+            group_ids: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Adds a detection and returns the aggregated detections whose window has closed.
+    pub fn push(&mut self, rule_index: usize, event: Value) -> Vec<(usize, Value)> {
+        let time = event
+            .get("eventTime")
+            .and_then(|t| t.as_str())
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let Some(time) = time else {
+            // detections without a valid time are sorted first and not aggregated
+            return vec![(rule_index, event)];
+        };
+        let mut closed = vec![];
+        while let Some(entry) = self.groups.first_entry() {
+            if time - entry.get().first <= self.window {
+                break;
+            }
+            let group = entry.remove();
+            self.group_ids.remove(&group.key);
+            closed.push(group.into_detection());
+        }
+        let key_values = self
+            .keys
+            .iter()
+            .map(|k| match get_json_value(&event, k) {
+                Some(Value::String(s)) => s.clone(),
+                Some(v) => v.to_string(),
+                None => String::new(),
+            })
+            .collect();
+        let key = (rule_index, key_values);
+        if let Some(group) = self
+            .group_ids
+            .get(&key)
+            .and_then(|id| self.groups.get_mut(id))
+        {
+            group.count += 1;
+            group.last = time;
+        } else {
+            self.group_ids.insert(key.clone(), self.next_id);
+            self.groups.insert(
+                self.next_id,
+                Group {
+                    key,
+                    event,
+                    first: time,
+                    last: time,
+                    count: 1,
+                },
+            );
+            self.next_id += 1;
+        }
+        closed
+    }
+
+    /// Returns the remaining aggregated detections.
+    pub fn finish(self) -> Vec<(usize, Value)> {
+        self.groups
+            .into_values()
+            .map(Group::into_detection)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_aggregator() -> DetectionAggregator {
+        let keys = vec!["sourceIPAddress".to_string()];
+        DetectionAggregator::new(Duration::minutes(10), keys)
+    }
+
+    fn event(time: &str, ip: &str) -> Value {
+        json!({"eventTime": time, "sourceIPAddress": ip})
+    }
+
+    #[test]
+    fn test_group_by_rule_and_keys() {
+        let mut agg = test_aggregator();
+        assert!(
+            agg.push(0, event("2024-08-18T13:00:00Z", "192.0.2.1"))
+                .is_empty()
+        );
+        assert!(
+            agg.push(0, event("2024-08-18T13:01:00Z", "192.0.2.2"))
+                .is_empty()
+        );
+        assert!(
+            agg.push(1, event("2024-08-18T13:02:00Z", "192.0.2.1"))
+                .is_empty()
+        );
+        assert!(
+            agg.push(0, event("2024-08-18T13:09:00Z", "192.0.2.1"))
+                .is_empty()
+        );
+        let out = agg.finish();
+        assert_eq!(out.len(), 3);
+        let info = &out[0].1[AGGREGATION_KEY];
+        assert_eq!(out[0].0, 0);
+        assert_eq!(info["count"], 2);
+        assert_eq!(info["firstEventTime"], "2024-08-18T13:00:00Z");
+        assert_eq!(info["lastEventTime"], "2024-08-18T13:09:00Z");
+        assert_eq!(out[1].1["sourceIPAddress"], "192.0.2.2");
+        assert_eq!(out[2].0, 1);
+    }
+
+    #[test]
+    fn test_group_closes_after_window() {
+        let mut agg = test_aggregator();
+        agg.push(0, event("2024-08-18T13:00:00Z", "192.0.2.1"));
+        let out = agg.push(0, event("2024-08-18T13:10:30Z", "192.0.2.1"));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].1[AGGREGATION_KEY]["count"], 1);
+        let out = agg.finish();
+        assert_eq!(
+            out[0].1[AGGREGATION_KEY]["firstEventTime"],
+            "2024-08-18T13:10:30Z"
+        );
+    }
+
+    #[test]
+    fn test_detection_without_time_is_not_aggregated() {
+        let mut agg = test_aggregator();
+        let out = agg.push(0, json!({"sourceIPAddress": "192.0.2.1"}));
+        assert_eq!(out.len(), 1);
+        assert!(out[0].1.get(AGGREGATION_KEY).is_none());
+        assert!(agg.finish().is_empty());
+    }
+}
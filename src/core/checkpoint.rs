@@ -0,0 +1,319 @@
+use crate::cmd::aws_detect::DetectionSummary;
+use crate::core::dedup::EventDeduplicator;
+use crate::option::cli::AwsCtTimelineOptions;
+use crate::output::syslog::SyslogTarget;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// The progress is saved at this interval while scanning, and when Ctrl-C is pressed.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Returns true if an interrupted scan with these options can be continued with --resume.
+/// The results written while scanning (all of them with --no-sort, and the syslog messages)
+/// must be in files that are truncated to the checkpoint when resuming: CSV/JSON/JSONL and
+/// syslog files. Detections sent over the network cannot be taken back, so HTTP and network
+/// syslog targets are not supported. The redaction key is not saved, so --redact needs
+/// --redact-key to give the same pseudonyms after resuming.
+pub fn is_resumable(options: &AwsCtTimelineOptions) -> bool {
+    let network_syslog = options
+        .syslog
+        .as_deref()
+        .is_some_and(|s| !matches!(SyslogTarget::parse(s), SyslogTarget::File(_)));
+    let written_while_scanning = options.no_sort || options.syslog.is_some();
+    let redact_opt = &options.redact_opt;
+    options.output.is_some()
+        && options.input_opt.directory.is_some()
+        && !options.follow
+        && !options.append
+        && options.state.is_none()
+        && options.http_opt.http_url.is_none()
+        && !network_syslog
+        && (!written_while_scanning || !redact_opt.redact || redact_opt.redact_key.is_some())
+        && (!options.no_sort
+            || (matches!(options.output_type, 1..=5)
+                && options.html_report.is_none()
+                && options.stix.is_none()
+                && options.pivot_keywords.is_none()))
+}
+
+pub fn checkpoint_dir(output: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.checkpoint", output.display()))
+}
+
+// The command line options must be the same to resume a scan.
+fn command_args() -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .filter(|arg| arg != "--resume")
+        .collect()
+}
+
+/// The progress of an aws-ct-timeline scan saved to the `<output>.checkpoint` directory:
+/// the scanned log files, the partial summary, the seen eventIDs, the sorted runs of detections
+/// and the sizes of the output files.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    args: Vec<String>,
+    generation: u64,
+    pub completed: BTreeSet<String>,
+    summary: Value,
+    timestamps: Vec<i64>,
+    outputs: BTreeMap<PathBuf, u64>,
+    pub runs: Vec<PathBuf>,
+    #[serde(skip)]
+    dir: PathBuf,
+    #[serde(skip)]
+    restored_summary: Option<DetectionSummary>,
+    #[serde(skip)]
+    pub dedup: Option<EventDeduplicator>,
+    #[serde(skip)]
+    last_saved: Option<Instant>,
+    #[serde(skip)]
+    pub interrupted: bool,
+}
+
+impl Checkpoint {
+    /// Starts a new scan. The checkpoint directory must not exist, so that the checkpoint of
+    /// another scan is never removed. An initial checkpoint is written at once, so the scan can
+    /// be resumed even if it is interrupted before the first checkpoint.
+    pub fn new(output: &Path) -> Result<Self, String> {
+        let dir = checkpoint_dir(output);
+        if let Err(e) = fs::create_dir(&dir) {
+            return Err(match e.kind() {
+                io::ErrorKind::AlreadyExists => format!(
+                    "{} already exists. Please run the same command with --resume to continue the interrupted scan, or remove the directory.",
+                    dir.display()
+                ),
+                _ => format!("Failed to create {}: {}", dir.display(), e),
+            });
+        }
+        let checkpoint = Checkpoint::start(dir);
+        checkpoint.write().map_err(|e| {
+            let path = checkpoint.dir.join(CHECKPOINT_FILE);
+            format!("Failed to write {}: {}", path.display(), e)
+        })?;
+        Ok(checkpoint)
+    }
+
+    fn start(dir: PathBuf) -> Self {
+        Checkpoint {
+            args: command_args(),
+            dir,
+            last_saved: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    /// Loads the checkpoint of an interrupted scan to the output. A checkpoint directory without
+    /// a checkpoint file was interrupted before the initial checkpoint, so the scan starts over.
+    pub fn load(output: &Path) -> Result<Self, String> {
+        let dir = checkpoint_dir(output);
+        let path = dir.join(CHECKPOINT_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && dir.is_dir() => {
+                return Ok(Checkpoint::start(dir));
+            }
+            Err(_) => {
+                return Err(format!(
+                    "Could not find a checkpoint of an interrupted scan in {}.",
+                    dir.display()
+                ));
+            }
+        };
+        let invalid = |e: &dyn std::fmt::Display| {
+            format!("Invalid checkpoint file {}: {}", path.display(), e)
+        };
+        let mut checkpoint: Checkpoint = serde_json::from_str(&content).map_err(|e| invalid(&e))?;
+        if checkpoint.args != command_args() {
+            return Err(
+                "The options are different from the interrupted scan. Please run the same command with --resume."
+                    .to_string(),
+            );
+        }
+        if !checkpoint.summary.is_null() {
+            let summary =
+                serde_json::from_value(checkpoint.summary.take()).map_err(|e| invalid(&e))?;
+            checkpoint.restored_summary = Some(summary);
+        }
+        checkpoint.dir = dir;
+        let dedup_path = checkpoint.dedup_path(checkpoint.generation);
+        if dedup_path.exists() {
+            let dedup = EventDeduplicator::load(&dedup_path).map_err(|e| invalid(&e))?;
+            checkpoint.dedup = Some(dedup);
+        }
+        checkpoint.last_saved = Some(Instant::now());
+        Ok(checkpoint)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn dedup_path(&self, generation: u64) -> PathBuf {
+        self.dir.join(format!("dedup-{}.bin", generation))
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_saved
+            .is_none_or(|t| t.elapsed() >= CHECKPOINT_INTERVAL)
+    }
+
+    /// Returns the summary of the interrupted scan (or an empty summary for a new scan).
+    pub fn take_summary(&mut self) -> DetectionSummary {
+        let mut summary = self.restored_summary.take().unwrap_or_default();
+        summary.timestamps = std::mem::take(&mut self.timestamps);
+        summary
+    }
+
+    /// Opens an output file written while scanning. When resuming, the results written after
+    /// the checkpoint are removed and the new results are appended to the file.
+    pub fn open_output(&mut self, path: &Path) -> io::Result<File> {
+        let offset = *self.outputs.entry(path.to_path_buf()).or_default();
+        if offset == 0 {
+            return File::create(path);
+        }
+        let file = fs::OpenOptions::new().append(true).open(path)?;
+        file.set_len(offset)?;
+        Ok(file)
+    }
+
+    pub fn offset(&self, path: &Path) -> u64 {
+        self.outputs.get(path).copied().unwrap_or_default()
+    }
+
+    /// Saves the progress. The output files must be flushed before.
+    pub fn save(
+        &mut self,
+        summary: &DetectionSummary,
+        dedup: Option<&EventDeduplicator>,
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        self.generation += 1;
+        if let Some(dedup) = dedup {
+            dedup.save(&self.dedup_path(self.generation))?;
+        }
+        self.summary = serde_json::to_value(summary)?;
+        self.timestamps = summary.timestamps.clone();
+        for (path, size) in self.outputs.iter_mut() {
+            *size = fs::metadata(path)?.len();
+        }
+        self.write()?;
+        fs::remove_file(self.dedup_path(self.generation - 1)).ok();
+        self.summary = Value::Null;
+        self.timestamps.clear();
+        self.last_saved = Some(Instant::now());
+        Ok(())
+    }
+
+    // The previous checkpoint is kept until the new one is complete.
+    fn write(&self) -> io::Result<()> {
+        let path = self.dir.join(CHECKPOINT_FILE);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Removes the checkpoint after the scan has finished.
+    pub fn remove(&self) {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn save_checkpoint(output: &Path) -> File {
+        let mut checkpoint = Checkpoint::new(output).unwrap();
+        let mut file = checkpoint.open_output(output).unwrap();
+        file.write_all(b"header\nrow1\n").unwrap();
+        checkpoint.completed.insert("log1.json".to_string());
+        let summary = DetectionSummary {
+            total_events: 3,
+            timestamps: vec![1, 2],
+            ..Default::default()
+        };
+        let mut dedup = EventDeduplicator::default();
+        dedup
+            .dedup(&mut vec![json!({"eventID": "a"}), json!({"eventID": "a"})])
+            .unwrap();
+        checkpoint.save(&summary, Some(&dedup)).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_load_without_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        assert!(Checkpoint::load(&output).is_err());
+    }
+
+    #[test]
+    fn test_load_before_first_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        let _checkpoint = Checkpoint::new(&output).unwrap();
+        let checkpoint = Checkpoint::load(&output).unwrap();
+        assert!(checkpoint.completed.is_empty());
+        assert!(checkpoint.dedup.is_none());
+
+        // interrupted between creating the directory and writing the initial checkpoint
+        fs::remove_file(checkpoint_dir(&output).join(CHECKPOINT_FILE)).unwrap();
+        let mut checkpoint = Checkpoint::load(&output).unwrap();
+        assert!(checkpoint.completed.is_empty());
+        assert_eq!(checkpoint.take_summary().total_events, 0);
+    }
+
+    #[test]
+    fn test_new_keeps_existing_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        save_checkpoint(&output);
+        assert!(Checkpoint::new(&output).is_err());
+        assert!(Checkpoint::load(&output).is_ok());
+    }
+
+    #[test]
+    fn test_load_restores_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        save_checkpoint(&output);
+
+        let mut checkpoint = Checkpoint::load(&output).unwrap();
+        assert!(checkpoint.completed.contains("log1.json"));
+        let summary = checkpoint.take_summary();
+        assert_eq!(summary.total_events, 3);
+        assert_eq!(summary.timestamps, vec![1, 2]);
+        let mut dedup = checkpoint.dedup.take().unwrap();
+        assert_eq!(dedup.duplicates, 1);
+        assert!(dedup.is_duplicate(&json!({"eventID": "a"})).unwrap());
+    }
+
+    #[test]
+    fn test_open_output_truncates_to_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        let mut file = save_checkpoint(&output);
+        // results written after the checkpoint are removed when resuming
+        file.write_all(b"row2\n").unwrap();
+
+        let mut checkpoint = Checkpoint::load(&output).unwrap();
+        let mut file = checkpoint.open_output(&output).unwrap();
+        file.write_all(b"row3\n").unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "header\nrow1\nrow3\n");
+
+        checkpoint.remove();
+        assert!(!checkpoint_dir(&output).exists());
+    }
+}
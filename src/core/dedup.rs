@@ -0,0 +1,370 @@
+use serde_json::Value;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// The eventIDs are kept in memory until this many events (about 32 MiB), then written to
+// sorted temp files (runs).
+const MAX_EXACT_EVENTS: usize = 1_000_000;
+// The runs are merged into one when there are more than this many, to bound the lookups.
+const MAX_RUNS: usize = 8;
+// The Bloom filter over the runs has 10 bits per event, where the false positive rate is about 1%.
+// A false positive only costs a lookup in the runs.
+const BLOOM_BITS_PER_EVENT: usize = 10;
+const BLOOM_HASHES: u64 = 7;
+
+struct BloomFilter {
+    bits: Vec<u64>,
+    capacity: usize,
+}
+
+impl BloomFilter {
+    fn new(capacity: usize) -> Self {
+        BloomFilter {
+            bits: vec![0; (capacity * BLOOM_BITS_PER_EVENT).div_ceil(64).max(1)],
+            capacity,
+        }
+    }
+
+    fn bit_positions(&self, hash: u128) -> impl Iterator<Item = (usize, u64)> + use<> {
+        let (h1, h2) = ((hash >> 64) as u64, hash as u64);
+        let num_bits = self.bits.len() as u64 * 64;
+        (0..BLOOM_HASHES).map(move |i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            ((bit / 64) as usize, 1 << (bit % 64))
+        })
+    }
+
+    fn insert(&mut self, hash: u128) {
+        for (word, mask) in self.bit_positions(hash) {
+            self.bits[word] |= mask;
+        }
+    }
+
+    fn contains(&self, hash: u128) -> bool {
+        self.bit_positions(hash)
+            .all(|(word, mask)| self.bits[word] & mask != 0)
+    }
+}
+
+/// A temp file of sorted eventID hashes.
+struct Run {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl Run {
+    fn write(path: PathBuf, hashes: impl Iterator<Item = io::Result<u128>>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let mut len = 0;
+        for hash in hashes {
+            writer.write_all(&hash?.to_le_bytes())?;
+            len += 1;
+        }
+        writer.flush()?;
+        let file = File::open(&path)?;
+        Ok(Run { path, file, len })
+    }
+
+    // Binary search in the file.
+    fn contains(&mut self, hash: u128) -> io::Result<bool> {
+        let (mut low, mut high) = (0, self.len);
+        let mut buf = [0; 16];
+        while low < high {
+            let mid = low + (high - low) / 2;
+            self.file.seek(SeekFrom::Start(mid * 16))?;
+            self.file.read_exact(&mut buf)?;
+            match u128::from_le_bytes(buf).cmp(&hash) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+
+    fn hashes(&self) -> io::Result<impl Iterator<Item = io::Result<u128>> + use<>> {
+        Ok(read_hashes(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+fn read_hashes(mut reader: impl Read) -> impl Iterator<Item = io::Result<u128>> {
+    std::iter::from_fn(move || {
+        let mut hash = [0; 16];
+        match reader.read_exact(&mut hash) {
+            Ok(()) => Some(Ok(u128::from_le_bytes(hash))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// Removes events whose eventID was already seen (ex: the same event in an organization trail
+/// and an account trail). The memory usage is bounded by writing the seen eventIDs to sorted
+/// temp files for huge inputs. A Bloom filter skips the lookups in the files for most new events,
+/// and its hits are always confirmed in the files, so a unique event is never removed.
+pub struct EventDeduplicator {
+    seen: HashSet<u128>,
+    max_exact: usize,
+    runs: Vec<Run>,
+    next_run: usize,
+    // The Bloom filter of the hashes in the runs
+    bloom: Option<BloomFilter>,
+    spilled: usize,
+    temp_dir: Option<TempDir>,
+    pub duplicates: usize,
+}
+
+impl Default for EventDeduplicator {
+    fn default() -> Self {
+        Self::with_max_exact(MAX_EXACT_EVENTS)
+    }
+}
+
+impl EventDeduplicator {
+    fn with_max_exact(max_exact: usize) -> Self {
+        EventDeduplicator {
+            seen: HashSet::new(),
+            max_exact,
+            runs: vec![],
+            next_run: 0,
+            bloom: None,
+            spilled: 0,
+            temp_dir: None,
+            duplicates: 0,
+        }
+    }
+
+    /// Returns true if an event with the same eventID was already seen.
+    /// Events without an eventID are never treated as duplicates.
+    pub fn is_duplicate(&mut self, event: &Value) -> io::Result<bool> {
+        let Some(id) = event.get("eventID").and_then(|id| id.as_str()) else {
+            return Ok(false);
+        };
+        let hash = hash_id(id);
+        let duplicate = self.seen.contains(&hash) || self.in_runs(hash)?;
+        if duplicate {
+            self.duplicates += 1;
+        } else {
+            self.insert(hash)?;
+        }
+        Ok(duplicate)
+    }
+
+    pub fn dedup(&mut self, events: &mut Vec<Value>) -> io::Result<()> {
+        let mut result = Ok(());
+        events.retain(|event| {
+            if result.is_err() {
+                return true;
+            }
+            match self.is_duplicate(event) {
+                Ok(duplicate) => !duplicate,
+                Err(e) => {
+                    result = Err(e);
+                    true
+                }
+            }
+        });
+        result
+    }
+
+    fn in_runs(&mut self, hash: u128) -> io::Result<bool> {
+        if !self.bloom.as_ref().is_some_and(|b| b.contains(hash)) {
+            return Ok(false);
+        }
+        for run in &mut self.runs {
+            if run.contains(hash)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn insert(&mut self, hash: u128) -> io::Result<()> {
+        self.seen.insert(hash);
+        if self.seen.len() >= self.max_exact {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let mut hashes: Vec<u128> = self.seen.drain().collect();
+        hashes.sort_unstable();
+        let run = Run::write(self.run_path()?, hashes.iter().copied().map(Ok))?;
+        self.runs.push(run);
+        self.spilled += hashes.len();
+        if self.runs.len() > MAX_RUNS {
+            self.merge_runs()?;
+        }
+        match &mut self.bloom {
+            Some(bloom) if self.spilled <= bloom.capacity => {
+                hashes.into_iter().for_each(|hash| bloom.insert(hash));
+            }
+            // the Bloom filter is rebuilt with twice the capacity, so the false positive rate
+            // stays bounded
+            _ => {
+                let mut bloom = BloomFilter::new(self.spilled * 2);
+                for run in &self.runs {
+                    for hash in run.hashes()? {
+                        bloom.insert(hash?);
+                    }
+                }
+                self.bloom = Some(bloom);
+            }
+        }
+        Ok(())
+    }
+
+    fn run_path(&mut self) -> io::Result<PathBuf> {
+        let dir = match &self.temp_dir {
+            Some(dir) => dir,
+            None => self
+                .temp_dir
+                .insert(tempfile::Builder::new().prefix("suzaku-dedup-").tempdir()?),
+        };
+        self.next_run += 1;
+        Ok(dir.path().join(format!("run-{}.bin", self.next_run)))
+    }
+
+    fn merge_runs(&mut self) -> io::Result<()> {
+        let runs = std::mem::take(&mut self.runs);
+        let mut sources = vec![];
+        for run in &runs {
+            sources.push(run.hashes()?);
+        }
+        let mut heap = BinaryHeap::new();
+        for (i, source) in sources.iter_mut().enumerate() {
+            if let Some(hash) = source.next() {
+                heap.push(Reverse((hash?, i)));
+            }
+        }
+        let merged = std::iter::from_fn(|| {
+            let Reverse((hash, i)) = heap.pop()?;
+            match sources[i].next() {
+                Some(Ok(next)) => heap.push(Reverse((next, i))),
+                Some(Err(e)) => return Some(Err(e)),
+                None => {}
+            }
+            Some(Ok(hash))
+        });
+        let run = Run::write(self.run_path()?, merged)?;
+        for run in runs {
+            fs::remove_file(&run.path).ok();
+        }
+        self.runs.push(run);
+        Ok(())
+    }
+
+    /// Saves the seen eventIDs to a binary file (for --resume).
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&(self.duplicates as u64).to_le_bytes())?;
+        for hash in &self.seen {
+            writer.write_all(&hash.to_le_bytes())?;
+        }
+        for run in &self.runs {
+            for hash in run.hashes()? {
+                writer.write_all(&hash?.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut dedup = Self::default();
+        let mut word = [0; 8];
+        reader.read_exact(&mut word)?;
+        dedup.duplicates = u64::from_le_bytes(word) as usize;
+        for hash in read_hashes(reader) {
+            dedup.insert(hash?)?;
+        }
+        Ok(dedup)
+    }
+}
+
+fn hash_id(id: &str) -> u128 {
+    let mut hashes = [0u64; 2];
+    for (seed, hash) in hashes.iter_mut().enumerate() {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        id.hash(&mut hasher);
+        *hash = hasher.finish();
+    }
+    ((hashes[0] as u128) << 64) | hashes[1] as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ids(ids: &[&str]) -> Vec<Value> {
+        ids.iter().map(|id| json!({ "eventID": id })).collect()
+    }
+
+    #[test]
+    fn test_dedup_events() {
+        let mut dedup = EventDeduplicator::with_max_exact(2);
+        let mut events = ids(&["a", "b", "a"]);
+        dedup.dedup(&mut events).unwrap();
+        assert_eq!(events, ids(&["a", "b"]));
+        assert_eq!(dedup.duplicates, 1);
+    }
+
+    #[test]
+    fn test_events_without_id_are_kept() {
+        let mut dedup = EventDeduplicator::default();
+        let mut events = vec![json!({"eventName": "no id"}), json!({"eventName": "no id"})];
+        dedup.dedup(&mut events).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(dedup.duplicates, 0);
+    }
+
+    #[test]
+    fn test_spilled_ids_are_kept() {
+        let mut dedup = EventDeduplicator::with_max_exact(2);
+        let mut events = ids(&["a", "b", "c", "a", "c", "d"]);
+        dedup.dedup(&mut events).unwrap();
+        assert!(!dedup.runs.is_empty());
+        assert_eq!(events, ids(&["a", "b", "c", "d"]));
+        assert_eq!(dedup.duplicates, 2);
+    }
+
+    #[test]
+    fn test_distinct_events_past_threshold_are_not_removed() {
+        let mut dedup = EventDeduplicator::with_max_exact(100);
+        let ids: Vec<String> = (0..20_000).map(|i| format!("event-{}", i)).collect();
+        let mut events: Vec<Value> = ids.iter().map(|id| json!({ "eventID": id })).collect();
+        dedup.dedup(&mut events).unwrap();
+        assert_eq!(events.len(), ids.len());
+        assert_eq!(dedup.duplicates, 0);
+        assert!(dedup.runs.len() <= MAX_RUNS);
+
+        dedup.dedup(&mut events).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(dedup.duplicates, ids.len());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dedup.bin");
+        for max_exact in [MAX_EXACT_EVENTS, 2] {
+            let mut dedup = EventDeduplicator::with_max_exact(max_exact);
+            dedup.dedup(&mut ids(&["a", "b", "c", "a"])).unwrap();
+            dedup.save(&path).unwrap();
+            let mut loaded = EventDeduplicator::load(&path).unwrap();
+            assert_eq!(loaded.duplicates, 1);
+            let mut events = ids(&["b", "e"]);
+            loaded.dedup(&mut events).unwrap();
+            assert_eq!(events, ids(&["e"]));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::option::cli::RedactOptions;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+// Number of hex characters of the keyed hash used in a pseudonym.
+const PSEUDONYM_LEN: usize = 12;
+// Shorter values are not replaced in the other strings to avoid false replacements.
+const MIN_SCRUB_LEN: usize = 4;
+
+/// Replaces the values of the configured fields with keyed-hash (HMAC-SHA256) pseudonyms.
+/// The same value always gets the same pseudonym with the same key, so correlations survive.
+pub struct Redactor {
+    key: Vec<u8>,
+    fields: Vec<String>,
+    profile_fields: HashSet<String>,
+    mapping: BTreeMap<String, String>,
+}
+
+impl Redactor {
+    pub fn new(opt: &RedactOptions) -> Result<Self, String> {
+        let fields: Vec<String> = fs::read_to_string(&opt.redact_fields)
+            .map_err(|e| format!("Could not read {}: {}", opt.redact_fields.display(), e))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let key = match &opt.redact_key {
+            Some(key) => key.as_bytes().to_vec(),
+            None => [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat(),
+        };
+        Ok(Self::with_key(key, fields))
+    }
+
+    fn with_key(key: Vec<u8>, fields: Vec<String>) -> Self {
+        Redactor {
+            key,
+            profile_fields: fields.iter().map(|f| format!(".{}", f)).collect(),
+            fields,
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    pub fn pseudonym(&mut self, value: &str) -> String {
+        if value.is_empty() || value == "-" {
+            return value.to_string();
+        }
+        let pseudonym = pseudonym(&self.key, value);
+        self.mapping
+            .entry(pseudonym.clone())
+            .or_insert_with(|| value.to_string());
+        pseudonym
+    }
+
+    /// Returns a copy of the event with the configured fields redacted.
+    /// The redacted values are also replaced in the other strings (ex: ARNs in errorMessage)
+    /// where they are not a part of a longer word or number.
+    pub fn redact_json(&mut self, json: &Value) -> Value {
+        let mut redacted = json.clone();
+        let mut replaced = vec![];
+        for field in &self.fields {
+            let target = field
+                .split('.')
+                .try_fold(&mut redacted, |v, k| v.get_mut(k));
+            if let Some(target) = target {
+                let value = match target {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    _ => continue,
+                };
+                if value.is_empty() || value == "-" {
+                    continue;
+                }
+                let pseudonym = pseudonym(&self.key, &value);
+                *target = Value::String(pseudonym.clone());
+                replaced.push((value, pseudonym));
+            }
+        }
+        for (value, pseudonym) in &replaced {
+            self.mapping
+                .entry(pseudonym.clone())
+                .or_insert_with(|| value.clone());
+        }
+        // replace longer values first so an ARN is not split by its account ID
+        replaced.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        replaced.retain(|(value, _)| value.len() >= MIN_SCRUB_LEN);
+        scrub(&mut redacted, &replaced);
+        redacted
+    }
+
+    /// Redacts the columns of a record whose profile value is one of the configured fields.
+    pub fn redact_record(&mut self, profile: &[(String, String)], record: &mut [String]) {
+        for ((_, v), col) in profile.iter().zip(record.iter_mut()) {
+            if self.profile_fields.contains(v) {
+                *col = self.pseudonym(col);
+            }
+        }
+    }
+
+    /// Saves the pseudonyms and the original values for de-anonymisation.
+    pub fn write_map(&self, path: &Path) -> io::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["Pseudonym", "Original"])?;
+        for (pseudonym, original) in &self.mapping {
+            wtr.write_record([pseudonym, original])?;
+        }
+        wtr.flush()
+    }
+}
+
+fn pseudonym(key: &[u8], value: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(value.as_bytes());
+    let hash: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("REDACTED-{}", &hash[..PSEUDONYM_LEN])
+}
+
+fn scrub(json: &mut Value, replaced: &[(String, String)]) {
+    match json {
+        Value::String(s) => {
+            for (value, pseudonym) in replaced {
+                if let Some(scrubbed) = replace_at_boundaries(s, value, pseudonym) {
+                    *s = scrubbed;
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| scrub(v, replaced)),
+        Value::Object(map) => map.values_mut().for_each(|v| scrub(v, replaced)),
+        _ => {}
+    }
+}
+
+// Replaces the occurrences of the value that are not preceded or followed by a letter or a digit,
+// so an account ID is replaced in an ARN but not in a longer ID. Returns None if nothing is replaced.
+fn replace_at_boundaries(s: &str, value: &str, pseudonym: &str) -> Option<String> {
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut scrubbed = String::new();
+    let mut last = 0;
+    for (start, _) in s.match_indices(value) {
+        let end = start + value.len();
+        if is_word(s[..start].chars().next_back()) || is_word(s[end..].chars().next()) {
+            continue;
+        }
+        scrubbed.push_str(&s[last..start]);
+        scrubbed.push_str(pseudonym);
+        last = end;
+    }
+    if last == 0 {
+        return None;
+    }
+    scrubbed.push_str(&s[last..]);
+    Some(scrubbed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_redactor(key: &[u8]) -> Redactor {
+        let fields = vec![
+            "sourceIPAddress".to_string(),
+            "userIdentity.accountId".to_string(),
+            "recipientAccountId".to_string(),
+        ];
+        Redactor::with_key(key.to_vec(), fields)
+    }
+
+    fn test_event() -> Value {
+        json!({
+            "eventName": "DeleteTrail",
+            "sourceIPAddress": "192.0.2.1",
+            "recipientAccountId": "123456789012",
+            "userIdentity": {"accountId": "123456789012"},
+            "errorMessage": "User: arn:aws:iam::123456789012:user/alice is not authorized"
+        })
+    }
+
+    #[test]
+    fn test_redact_fields() {
+        let redacted = test_redactor(b"secret").redact_json(&test_event());
+        let pseudonym = redacted["recipientAccountId"].as_str().unwrap();
+        assert!(pseudonym.starts_with("REDACTED-"));
+        assert_eq!(redacted["userIdentity"]["accountId"], pseudonym);
+        assert_ne!(redacted["sourceIPAddress"], "192.0.2.1");
+        assert_eq!(redacted["eventName"], "DeleteTrail");
+    }
+
+    #[test]
+    fn test_redacted_values_are_scrubbed_from_other_fields() {
+        let redacted = test_redactor(b"secret").redact_json(&test_event());
+        let pseudonym = redacted["recipientAccountId"].as_str().unwrap();
+        assert_eq!(
+            redacted["errorMessage"],
+            format!(
+                "User: arn:aws:iam::{}:user/alice is not authorized",
+                pseudonym
+            )
+        );
+    }
+
+    #[test]
+    fn test_values_in_longer_words_are_not_scrubbed() {
+        let mut event = test_event();
+        event["requestParameters"] = json!({
+            "ipAddress": "192.0.2.10",
+            "accessKeyId": "AKIA1234567890123",
+            "policy": "arn:aws:s3:::123456789012-logs/*"
+        });
+        let redacted = test_redactor(b"secret").redact_json(&event);
+        let pseudonym = redacted["recipientAccountId"].as_str().unwrap();
+        assert_eq!(redacted["requestParameters"]["ipAddress"], "192.0.2.10");
+        assert_eq!(
+            redacted["requestParameters"]["accessKeyId"],
+            "AKIA1234567890123"
+        );
+        assert_eq!(
+            redacted["requestParameters"]["policy"],
+            format!("arn:aws:s3:::{}-logs/*", pseudonym)
+        );
+    }
+
+    #[test]
+    fn test_redact_record() {
+        let mut redactor = test_redactor(b"secret");
+        let profile = vec![
+            ("SrcIP".to_string(), ".sourceIPAddress".to_string()),
+            ("EventName".to_string(), ".eventName".to_string()),
+        ];
+        let mut record = vec!["192.0.2.1".to_string(), "DeleteTrail".to_string()];
+        redactor.redact_record(&profile, &mut record);
+        assert_eq!(record[0], redactor.pseudonym("192.0.2.1"));
+        assert_eq!(record[1], "DeleteTrail");
+    }
+
+    #[test]
+    fn test_pseudonyms_depend_on_key() {
+        let pseudonym = test_redactor(b"secret").pseudonym("192.0.2.1");
+        assert_eq!(test_redactor(b"secret").pseudonym("192.0.2.1"), pseudonym);
+        assert_ne!(test_redactor(b"other").pseudonym("192.0.2.1"), pseudonym);
+    }
+
+    #[test]
+    fn test_write_map() {
+        let mut redactor = test_redactor(b"secret");
+        let pseudonym = redactor.pseudonym("192.0.2.1");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("map.csv");
+        redactor.write_map(&path).unwrap();
+        let map = fs::read_to_string(path).unwrap();
+        assert!(map.contains(&format!("{},192.0.2.1", pseudonym)));
+    }
+}
@@ -4,11 +4,19 @@ use std::fs;
 use std::path::PathBuf;
 
 pub fn load_rules_from_dir(path: &PathBuf) -> Vec<Rule> {
+    load_rules_with_path(path)
+        .into_iter()
+        .map(|(rule, _)| rule)
+        .collect()
+}
+
+/// Loads the rules together with the path of their YAML file.
+pub fn load_rules_with_path(path: &PathBuf) -> Vec<(Rule, PathBuf)> {
     let mut rules = Vec::new();
     if path.is_file() {
         if let Ok(contents) = fs::read_to_string(path) {
             if let Ok(rule) = rule_from_yaml(&contents) {
-                rules.push(rule);
+                rules.push((rule, path.clone()));
             }
         }
         return rules;
@@ -17,14 +25,14 @@ pub fn load_rules_from_dir(path: &PathBuf) -> Vec<Rule> {
     rules
 }
 
-fn load_rules_recursive(directory: &PathBuf, rules: &mut Vec<Rule>) {
+fn load_rules_recursive(directory: &PathBuf, rules: &mut Vec<(Rule, PathBuf)>) {
     if let Ok(entries) = fs::read_dir(directory) {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("yml") {
                 if let Ok(contents) = fs::read_to_string(&path) {
                     if let Ok(rule) = rule_from_yaml(&contents) {
-                        rules.push(rule);
+                        rules.push((rule, path));
                     }
                 }
             } else if path.is_dir() {
@@ -34,7 +42,7 @@ fn load_rules_recursive(directory: &PathBuf, rules: &mut Vec<Rule>) {
     }
 }
 
-fn level_to_int(level: &str) -> u8 {
+pub fn level_to_int(level: &str) -> u8 {
     match level.to_lowercase().as_str() {
         "info" | "informational" => 1,
         "low" => 2,
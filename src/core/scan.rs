@@ -1,7 +1,11 @@
-use crate::cmd::aws_detect::{DetectionSummary, Writers};
+use crate::cmd::aws_detect::{DetectionSummary, RecordTarget, Writers};
+use crate::core::checkpoint::Checkpoint;
 use crate::core::color::SuzakuColor::{Green, Orange};
-use crate::core::util::p;
-use crate::option::cli::{AwsCtTimelineOptions, CommonOptions, TimeOption};
+use crate::core::dedup::EventDeduplicator;
+use crate::core::state::ScanState;
+use crate::core::util::{INSIGHT_CATEGORY, event_category, p};
+use crate::option::cli::{AwsCtTimelineOptions, CommonOptions, InputOption, TimeOption};
+use crate::option::fieldfilter::filter_by_fields;
 use crate::option::geoip::GeoIPSearch;
 use crate::option::timefiler::filter_by_time;
 use bytesize::ByteSize;
@@ -10,6 +14,8 @@ use colored::Colorize;
 use console::style;
 use flate2::read::GzDecoder;
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use notify::{RecursiveMode, Watcher};
+use num_format::{Locale, ToFormattedString};
 use rayon::iter::IndexedParallelIterator;
 use rayon::iter::ParallelIterator;
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator};
@@ -18,14 +24,16 @@ use sigma_rust::{Event, Rule, event_from_json};
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{fs, io};
 
 // TODO remove allow
 #[allow(clippy::too_many_arguments)]
-pub fn scan_file(
-    f: &PathBuf,
+pub fn scan_input(
     options: &AwsCtTimelineOptions,
     rules: &Vec<&Rule>,
     summary: &mut DetectionSummary,
@@ -33,22 +41,174 @@ pub fn scan_file(
     wrt: &mut Writers,
     common_opt: &CommonOptions,
     geo: &mut Option<GeoIPSearch>,
-) {
-    let log_contents = get_content(f);
-    let events = match load_json_from_file(&log_contents) {
-        Ok(value) => value,
-        Err(_e) => return,
+    state: Option<&mut ScanState>,
+    checkpoint: Option<&mut Checkpoint>,
+) -> Result<Option<EventDeduplicator>, Box<dyn Error>> {
+    let handler = TimelineHandler {
+        options,
+        rules,
+        summary,
+        profile,
+        wrt,
+        common_opt,
+        geo,
     };
+    let dedup = process_events_from_input(
+        handler,
+        &options.input_opt,
+        options.output.is_some(),
+        common_opt.no_color,
+        state,
+        checkpoint,
+    )?;
+    summary.duplicate_events = dedup.as_ref().map_or(0, |d| d.duplicates);
+    Ok(dedup)
+}
 
-    detect_events(
-        &events, options, rules, summary, profile, wrt, common_opt, geo,
-    );
+/// Receives the events of each log file from `process_events_from_input`.
+pub trait EventHandler {
+    fn handle(&mut self, events: &[Value]) -> io::Result<()>;
+
+    /// Saves the progress after a log file has been processed (only called with a checkpoint).
+    fn checkpoint(
+        &mut self,
+        _checkpoint: &mut Checkpoint,
+        _dedup: Option<&EventDeduplicator>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-// TODO remove allow
+impl<F: FnMut(&[Value])> EventHandler for F {
+    fn handle(&mut self, events: &[Value]) -> io::Result<()> {
+        self(events);
+        Ok(())
+    }
+}
+
+struct TimelineHandler<'a> {
+    options: &'a AwsCtTimelineOptions,
+    rules: &'a Vec<&'a Rule>,
+    summary: &'a mut DetectionSummary,
+    profile: &'a [(String, String)],
+    wrt: &'a mut Writers,
+    common_opt: &'a CommonOptions,
+    geo: &'a mut Option<GeoIPSearch>,
+}
+
+impl EventHandler for TimelineHandler<'_> {
+    fn handle(&mut self, events: &[Value]) -> io::Result<()> {
+        detect_events(
+            events,
+            self.options,
+            self.rules,
+            self.summary,
+            self.profile,
+            self.wrt,
+            self.common_opt,
+            self.geo,
+        )
+    }
+
+    fn checkpoint(
+        &mut self,
+        checkpoint: &mut Checkpoint,
+        dedup: Option<&EventDeduplicator>,
+    ) -> io::Result<()> {
+        self.wrt.flush()?;
+        if let Some(sorter) = &mut self.wrt.sorter {
+            checkpoint.runs = sorter.checkpoint()?;
+        }
+        checkpoint.save(self.summary, dedup)
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl-C to stop scanning at a safe point. Pressing it again exits immediately.
+fn catch_interrupt() -> Result<(), ctrlc::Error> {
+    let handler = || {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    };
+    match ctrlc::set_handler(handler) {
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Loads the events of the input file or directory and passes them to the `handler`.
+/// Events with an already seen eventID are removed unless --keep-duplicates is specified,
+/// and events not passing the field filters (ex: --include-field, --event-category) or --where are skipped.
+/// With a `state`, the unchanged files of the directory are skipped (see --state).
+/// With a `checkpoint`, the files scanned before are skipped and the progress is saved
+/// periodically and when Ctrl-C is pressed (see --resume).
+/// Returns the deduplicator with the seen eventIDs (None with --keep-duplicates).
+pub fn process_events_from_input<H: EventHandler>(
+    mut handler: H,
+    input_opt: &InputOption,
+    show_progress: bool,
+    no_color: bool,
+    state: Option<&mut ScanState>,
+    mut checkpoint: Option<&mut Checkpoint>,
+) -> Result<Option<EventDeduplicator>, Box<dyn Error>> {
+    let mut dedup = (!input_opt.keep_duplicates).then(|| {
+        checkpoint
+            .as_deref_mut()
+            .and_then(|c| c.dedup.take())
+            .unwrap_or_default()
+    });
+    if let Some(d) = &input_opt.directory {
+        if checkpoint.is_some() {
+            catch_interrupt()?;
+        }
+        process_events_from_dir(
+            &mut handler,
+            input_opt,
+            &mut dedup,
+            d,
+            show_progress,
+            no_color,
+            state,
+            checkpoint.as_deref_mut(),
+        )?;
+    } else if let Some(f) = &input_opt.filepath {
+        if let Ok(mut events) = load_json_from_file(&get_content(f)) {
+            filter_events(&mut events, input_opt, &mut dedup)?;
+            handler.handle(&events)?;
+        }
+    }
+    let interrupted = checkpoint.is_some_and(|c| c.interrupted);
+    if let Some(d) = dedup.as_ref().filter(|d| d.duplicates > 0 && !interrupted) {
+        p(Green.rdg(no_color), "Duplicate events removed: ", false);
+        p(None, &d.duplicates.to_formatted_string(&Locale::en), true);
+        println!();
+    }
+    Ok(dedup)
+}
+
+fn filter_events(
+    events: &mut Vec<Value>,
+    input_opt: &InputOption,
+    dedup: &mut Option<EventDeduplicator>,
+) -> io::Result<()> {
+    if let Some(dedup) = dedup {
+        dedup.dedup(events)?;
+    }
+    events.retain(|event| filter_by_fields(input_opt, event));
+    if let Some(expr) = &input_opt.where_expr {
+        events.retain(|event| expr.is_match(event));
+    }
+    Ok(())
+}
+
+/// Watches the input directory and scans new or changed log files until Ctrl-C is pressed (--follow).
+/// `dedup` is the deduplicator of the initial scan, so the events already scanned are not detected again.
+/// File system notifications are used when available, and the directory is also polled
+/// every `--poll-interval` seconds in case notifications are missed or not supported.
 #[allow(clippy::too_many_arguments)]
-pub fn scan_directory(
-    d: &PathBuf,
+pub fn follow_input(
     options: &AwsCtTimelineOptions,
     rules: &Vec<&Rule>,
     summary: &mut DetectionSummary,
@@ -56,33 +216,115 @@ pub fn scan_directory(
     wrt: &mut Writers,
     common_opt: &CommonOptions,
     geo: &mut Option<GeoIPSearch>,
-) {
-    let process_events = |events: &[Value]| {
-        detect_events(
-            events, options, rules, summary, profile, wrt, common_opt, geo,
+    state: &mut ScanState,
+    mut dedup: Option<EventDeduplicator>,
+) -> Result<(), Box<dyn Error>> {
+    let no_color = common_opt.no_color;
+    let Some(dir) = &options.input_opt.directory else {
+        return Ok(());
+    };
+    catch_interrupt()?;
+
+    let (tx, rx) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if res.is_ok_and(|e| e.kind.is_create() || e.kind.is_modify()) {
+            tx.send(()).ok();
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+    if watcher.is_err() {
+        p(
+            Orange.rdg(no_color),
+            "File system notifications are not available. Polling the directory instead.",
+            true,
         );
+    }
+    let msg = format!(
+        "Watching {} for new log files. Press Ctrl-C to stop.",
+        dir.display()
+    );
+    p(Green.rdg(no_color), &msg, true);
+    println!();
+
+    let poll_interval = Duration::from_secs(options.poll_interval);
+    let debug = common_opt.debug;
+    let skip_error = |path: &Path, e: io::Error| {
+        if debug {
+            println!("Skipped {}: {}", path.display(), e);
+        }
     };
-    process_events_from_dir(
-        process_events,
-        d,
-        options.output.is_some(),
-        common_opt.no_color,
-    )
-    .unwrap();
+    let mut last_scan = Instant::now();
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let notified = if watcher.is_ok() {
+            rx.recv_timeout(Duration::from_secs(1)).is_ok()
+        } else {
+            thread::sleep(Duration::from_secs(1));
+            false
+        };
+        if !notified && last_scan.elapsed() < poll_interval {
+            continue;
+        }
+        // wait a moment for the files being written
+        thread::sleep(Duration::from_millis(500));
+        while rx.try_recv().is_ok() {}
+        last_scan = Instant::now();
+
+        let (_, file_paths, _) = count_files_recursive(dir, Some(&skip_error))?;
+        let mut scanned = 0;
+        for path in file_paths {
+            if !path.ends_with("json") && !path.ends_with("gz") {
+                continue;
+            }
+            // the file may have been removed
+            let Ok(Some(file_state)) = state.check(&path) else {
+                continue;
+            };
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            let Some(file_state) = state.check_contents(&path, file_state, &bytes) else {
+                continue;
+            };
+            // an incomplete file is not recorded and scanned again later
+            let Ok(log_contents) = decode_log(&path, bytes) else {
+                continue;
+            };
+            let mut events = log_contents_to_events(&log_contents);
+            state.record(&path, file_state);
+            filter_events(&mut events, &options.input_opt, &mut dedup)?;
+            detect_events(
+                &events, options, rules, summary, profile, wrt, common_opt, geo,
+            )?;
+            scanned += 1;
+            p(Green.rdg(no_color), "Scanned: ", false);
+            p(None, &format!("{} ({} events)", path, events.len()), true);
+        }
+        if scanned > 0 {
+            wrt.flush()?;
+            if let Some(path) = &options.state {
+                state.save(path)?;
+            }
+        }
+    }
+    println!();
+    Ok(())
 }
 
-// TODO remove allow
 #[allow(clippy::too_many_arguments)]
-pub fn process_events_from_dir<F>(
-    mut process_events: F,
-    directory: &PathBuf,
+fn process_events_from_dir<H: EventHandler>(
+    handler: &mut H,
+    input_opt: &InputOption,
+    dedup: &mut Option<EventDeduplicator>,
+    directory: &Path,
     show_progress: bool,
     no_color: bool,
-) -> Result<(), Box<dyn Error>>
-where
-    F: FnMut(&[Value]),
-{
-    let (count, file_paths, total_size) = count_files_recursive(directory)?;
+    mut state: Option<&mut ScanState>,
+    mut checkpoint: Option<&mut Checkpoint>,
+) -> Result<(), Box<dyn Error>> {
+    let (count, file_paths, total_size) = count_files_recursive(directory, None)?;
     let size = ByteSize::b(total_size).display().to_string();
 
     p(Green.rdg(no_color), "Total log files: ", false);
@@ -91,6 +333,19 @@ where
     p(None, size.to_string().as_str(), true);
     println!();
 
+    if let Some(checkpoint) = checkpoint.as_deref().filter(|c| !c.completed.is_empty()) {
+        p(
+            Green.rdg(no_color),
+            "Resuming the interrupted scan. Log files already scanned: ",
+            false,
+        );
+        p(
+            None,
+            &checkpoint.completed.len().to_formatted_string(&Locale::en),
+            true,
+        );
+        println!();
+    }
     p(Orange.rdg(no_color), "Scanning now. Please wait.", true);
     println!();
 
@@ -115,28 +370,91 @@ where
         pb.enable_steady_tick(Duration::from_millis(300));
     }
 
+    // unreadable files (ex: removed during the scan) are skipped
+    let warn_unreadable = |path: &str, e: io::Error| {
+        let msg = format!("Could not read {}: {}. The file was skipped.", path, e);
+        pb.suspend(|| p(Orange.rdg(no_color), &msg, true));
+    };
     for path in file_paths {
         if show_progress {
-            let size = fs::metadata(&path).unwrap().len();
+            let size = fs::metadata(&path).map_or(0, |m| m.len());
             let size = ByteSize::b(size).display().to_string();
             let pb_msg = format!("{} ({})", path, size);
             pb.set_message(pb_msg);
         }
-        let log_contents = if path.ends_with("json") {
-            fs::read_to_string(&path)?
-        } else if path.ends_with("gz") {
-            read_gz_file(&PathBuf::from(&path))?
-        } else {
+        if !path.ends_with("json") && !path.ends_with("gz") {
+            pb.inc(1);
+            continue;
+        }
+        if checkpoint
+            .as_deref()
+            .is_some_and(|c| c.completed.contains(&path))
+        {
             pb.inc(1);
             continue;
+        }
+        let mut file_state = None;
+        if let Some(state) = state.as_deref_mut() {
+            match state.check(&path) {
+                Ok(Some(s)) => file_state = Some(s),
+                Ok(None) => {
+                    pb.inc(1);
+                    continue;
+                }
+                Err(e) => {
+                    warn_unreadable(&path, e);
+                    pb.inc(1);
+                    continue;
+                }
+            }
+        }
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn_unreadable(&path, e);
+                pb.inc(1);
+                continue;
+            }
+        };
+        // the contents are hashed to skip the files only touched since the last scan
+        if let (Some(state), Some(s)) = (state.as_deref_mut(), file_state.take()) {
+            file_state = state.check_contents(&path, s, &bytes);
+            if file_state.is_none() {
+                pb.inc(1);
+                continue;
+            }
+        }
+        let log_contents = match decode_log(&path, bytes) {
+            Ok(log_contents) => log_contents,
+            Err(e) => {
+                warn_unreadable(&path, e);
+                pb.inc(1);
+                continue;
+            }
         };
 
-        let events = log_contents_to_events(&log_contents);
-        process_events(&events);
+        let mut events = log_contents_to_events(&log_contents);
+        if let (Some(state), Some(file_state)) = (state.as_deref_mut(), file_state) {
+            state.record(&path, file_state);
+        }
+        filter_events(&mut events, input_opt, dedup)?;
+        handler.handle(&events)?;
 
         if show_progress {
             pb.inc(1);
         }
+        if let Some(checkpoint) = checkpoint.as_deref_mut() {
+            checkpoint.completed.insert(path);
+            let interrupted = INTERRUPTED.load(Ordering::SeqCst);
+            if interrupted || checkpoint.is_due() {
+                handler.checkpoint(checkpoint, dedup.as_ref())?;
+            }
+            if interrupted {
+                checkpoint.interrupted = true;
+                pb.abandon();
+                return Ok(());
+            }
+        }
     }
     if show_progress {
         if no_color {
@@ -189,7 +507,7 @@ fn detect_events(
     wrt: &mut Writers,
     common_opt: &CommonOptions,
     geo: &mut Option<GeoIPSearch>,
-) {
+) -> io::Result<()> {
     // If all the events are loaded at once, it can consume too much memory.
     // To avoid the problem, we split the events into chunks.
     const CHUNK_SIZE: usize = 1000;
@@ -216,13 +534,14 @@ fn detect_events(
             .collect();
 
         // conduct rule's matches and return pairs of json_event and matched_rules
-        let results: Vec<(&Value, &Event, Vec<&Rule>)> = json_events
+        let results: Vec<(&Value, &Event, Vec<usize>)> = json_events
             .par_iter()
             .map(|(event, json_event)| {
-                let matched_rules: Vec<&Rule> = rules
+                let matched_rules: Vec<usize> = rules
                     .par_iter()
-                    .filter(move |rule| rule.is_match(json_event))
-                    .map(|rule| *rule)
+                    .enumerate()
+                    .filter(move |(_, rule)| rule.is_match(json_event))
+                    .map(|(i, _)| i)
                     .collect();
                 (*event, json_event, matched_rules)
             })
@@ -235,21 +554,43 @@ fn detect_events(
             .filter(|(_, _, matched_rules)| !matched_rules.is_empty())
             .count();
         summary.total_events += json_events.len();
+        for (event, _, matched_rules) in &results {
+            let category = event_category(event);
+            *summary
+                .category_events
+                .entry(category.to_string())
+                .or_default() += 1;
+            if !matched_rules.is_empty() {
+                *summary
+                    .category_hits
+                    .entry(category.to_string())
+                    .or_default() += 1;
+            }
+            if category == INSIGHT_CATEGORY {
+                summary.insights.add(event);
+            }
+        }
 
         // The post-processing contains codes that shouldn't be executed in parallel, like setting values to variable summary, so please don't use rayon here.
         for (event, json_event, matched_rules) in results {
-            for rule in matched_rules {
-                // write to console
-                crate::cmd::aws_detect::write_record(
-                    profile,
-                    json_event,
+            if matched_rules.is_empty() && options.all_events {
+                output_event(
+                    event, json_event, None, rules, profile, wrt, options, common_opt, geo,
+                )?;
+            }
+            for rule_index in matched_rules {
+                let rule = rules[rule_index];
+                output_event(
                     event,
-                    rule,
+                    json_event,
+                    Some(rule_index),
+                    rules,
+                    profile,
                     wrt,
-                    common_opt.no_color,
+                    options,
+                    common_opt,
                     geo,
-                    options.raw_output,
-                );
+                )?;
 
                 // add information to summary
                 if let Some(author) = &rule.author {
@@ -302,25 +643,106 @@ fn detect_events(
             }
         }
     }
+    Ok(())
 }
 
-fn count_files_recursive(directory: &PathBuf) -> Result<(usize, Vec<String>, u64), Box<dyn Error>> {
+#[allow(clippy::too_many_arguments)]
+fn output_event(
+    event: &Value,
+    json_event: &Event,
+    rule_index: Option<usize>,
+    rules: &[&Rule],
+    profile: &[(String, String)],
+    wrt: &mut Writers,
+    options: &AwsCtTimelineOptions,
+    common_opt: &CommonOptions,
+    geo: &mut Option<GeoIPSearch>,
+) -> io::Result<()> {
+    let rule = rule_index.map(|i| rules[i]);
+    if let Some(sorter) = &mut wrt.sorter {
+        // the sorted results are written after all events have been scanned,
+        // and the detections are sent to syslog and HTTP while scanning
+        let title = rule.map(|r| r.title.as_str()).unwrap_or_default();
+        sorter.push(rule_index, title, event)?;
+        if rule.is_some() && wrt.has_sinks() {
+            crate::cmd::aws_detect::write_record(
+                profile,
+                json_event,
+                event,
+                rule,
+                wrt,
+                common_opt.no_color,
+                geo,
+                options.raw_output,
+                RecordTarget::SINKS,
+            )?;
+        }
+    } else {
+        crate::cmd::aws_detect::write_record(
+            profile,
+            json_event,
+            event,
+            rule,
+            wrt,
+            common_opt.no_color,
+            geo,
+            options.raw_output,
+            RecordTarget::ALL,
+        )?;
+    }
+    Ok(())
+}
+
+// While following, the files may be removed between listing and reading a directory. The
+// errors are passed to `skip_error` and the entries are skipped when it is given.
+type SkipError<'a> = &'a dyn Fn(&Path, io::Error);
+
+fn count_files_recursive(
+    directory: &Path,
+    skip_error: Option<SkipError>,
+) -> Result<(usize, Vec<String>, u64), Box<dyn Error>> {
+    let skip = |path: &Path, e: io::Error| match skip_error {
+        Some(skip_error) => {
+            skip_error(path, e);
+            Ok(())
+        }
+        None => Err(e),
+    };
     let mut count = 0;
     let mut paths = Vec::new();
     let mut total_size = 0;
-    for entry in fs::read_dir(directory)? {
-        let entry = entry?;
-        let path = entry.path();
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            skip(directory, e)?;
+            return Ok((count, paths, total_size));
+        }
+    };
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                skip(directory, e)?;
+                continue;
+            }
+        };
         if path.is_file() {
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
                 if ext == "json" || ext == "gz" {
+                    let metadata = match fs::metadata(&path) {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            skip(&path, e)?;
+                            continue;
+                        }
+                    };
                     count += 1;
-                    total_size += fs::metadata(&path)?.len();
+                    total_size += metadata.len();
                     paths.push(path.to_str().unwrap().to_string());
                 }
             }
         } else if path.is_dir() {
-            let (sub_count, sub_paths, sub_size) = count_files_recursive(&path)?;
+            let (sub_count, sub_paths, sub_size) = count_files_recursive(&path, skip_error)?;
             count += sub_count;
             total_size += sub_size;
             paths.extend(sub_paths);
@@ -329,6 +751,17 @@ fn count_files_recursive(directory: &PathBuf) -> Result<(usize, Vec<String>, u64
     Ok((count, paths, total_size))
 }
 
+// Decompresses a gz log file or decodes a json log file.
+fn decode_log(path: &str, bytes: Vec<u8>) -> io::Result<String> {
+    if path.ends_with("gz") {
+        let mut contents = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
 pub fn read_gz_file(file_path: &PathBuf) -> io::Result<String> {
     let file = File::open(file_path)?;
     let mut decoder = GzDecoder::new(BufReader::new(file));
@@ -393,4 +826,19 @@ mod tests {
         let event = result.unwrap();
         assert_eq!(event.len(), 29);
     }
+
+    #[test]
+    fn test_count_files_skips_removed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.json"), "{}").unwrap();
+        let removed = dir.path().join("removed");
+        assert!(count_files_recursive(&removed, None).is_err());
+
+        let skipped = std::cell::Cell::new(0);
+        let skip_error = |_: &Path, _: io::Error| skipped.set(skipped.get() + 1);
+        let (count, _, _) = count_files_recursive(&removed, Some(&skip_error)).unwrap();
+        assert_eq!((count, skipped.get()), (0, 1));
+        let (count, _, size) = count_files_recursive(dir.path(), Some(&skip_error)).unwrap();
+        assert_eq!((count, size, skipped.get()), (1, 2, 1));
+    }
 }
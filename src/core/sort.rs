@@ -0,0 +1,290 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// Detections are kept in memory until they reach this size, then spilled to temp files.
+pub const SORT_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+
+struct SortRecord {
+    time: Option<DateTime<Utc>>,
+    rule_title: String,
+    rule_index: Option<usize>,
+    event: String,
+}
+
+impl SortRecord {
+    fn from_line(line: &str) -> Option<Self> {
+        let mut cols = line.splitn(4, '\t');
+        let time = parse_time(cols.next()?);
+        let rule_index = match cols.next()? {
+            "-" => None,
+            i => Some(i.parse().ok()?),
+        };
+        let rule_title = cols.next()?.to_string();
+        let event = cols.next()?.to_string();
+        Some(SortRecord {
+            time,
+            rule_title,
+            rule_index,
+            event,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        // The event is compact JSON, so it never contains a raw tab or newline.
+        let time = self.time.map(|t| t.to_rfc3339()).unwrap_or("-".to_string());
+        let title = self.rule_title.replace(['\t', '\n'], " ");
+        let rule_index = self
+            .rule_index
+            .map(|i| i.to_string())
+            .unwrap_or("-".to_string());
+        format!("{}\t{}\t{}\t{}", time, rule_index, title, self.event)
+    }
+
+    fn size(&self) -> usize {
+        self.event.len() + self.rule_title.len() + size_of::<SortRecord>()
+    }
+}
+
+impl PartialEq for SortRecord {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortRecord {}
+
+impl PartialOrd for SortRecord {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortRecord {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .cmp(&other.time)
+            .then_with(|| self.rule_title.cmp(&other.rule_title))
+            .then_with(|| self.rule_index.cmp(&other.rule_index))
+    }
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Sorts detections by eventTime and rule title.
+/// When the buffered detections exceed the memory budget, they are sorted and written to
+/// temporary files (runs), which are merged when the results are read back.
+pub struct DetectionSorter {
+    records: Vec<SortRecord>,
+    records_size: usize,
+    memory_budget: usize,
+    runs: Vec<PathBuf>,
+    temp_dir: Option<TempDir>,
+    // The runs are written here instead of a temp directory when checkpointing.
+    run_dir: Option<PathBuf>,
+}
+
+impl DetectionSorter {
+    pub fn new(memory_budget: usize) -> Self {
+        DetectionSorter {
+            records: vec![],
+            records_size: 0,
+            memory_budget,
+            runs: vec![],
+            temp_dir: None,
+            run_dir: None,
+        }
+    }
+
+    /// Keeps the runs in `dir` so that they survive an interrupted scan (see --resume).
+    /// `runs` are the runs saved by the previous checkpoint.
+    pub fn with_run_dir(mut self, dir: &Path, runs: Vec<PathBuf>) -> Self {
+        self.run_dir = Some(dir.to_path_buf());
+        self.runs = runs;
+        self
+    }
+
+    /// Writes the buffered detections to a run and returns all the runs to save in the checkpoint.
+    pub fn checkpoint(&mut self) -> io::Result<Vec<PathBuf>> {
+        self.spill()?;
+        Ok(self.runs.clone())
+    }
+
+    /// Pushes an event with the index of the matched rule (None for events without detections).
+    pub fn push(
+        &mut self,
+        rule_index: Option<usize>,
+        rule_title: &str,
+        event: &Value,
+    ) -> io::Result<()> {
+        let time = event
+            .get("eventTime")
+            .and_then(|t| t.as_str())
+            .and_then(parse_time);
+        let record = SortRecord {
+            time,
+            rule_title: rule_title.to_string(),
+            rule_index,
+            event: event.to_string(),
+        };
+        self.records_size += record.size();
+        self.records.push(record);
+        if self.records_size > self.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+        if self.temp_dir.is_none() && self.run_dir.is_none() {
+            self.temp_dir = Some(tempfile::Builder::new().prefix("suzaku-sort-").tempdir()?);
+        }
+        let dir = match &self.run_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                dir.as_path()
+            }
+            None => self.temp_dir.as_ref().unwrap().path(),
+        };
+        let path = dir.join(format!("run-{}.tsv", self.runs.len()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        self.records.sort();
+        for record in self.records.drain(..) {
+            writeln!(writer, "{}", record.to_line())?;
+        }
+        writer.flush()?;
+        self.records_size = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// Returns the (rule index, event) pairs in sorted order.
+    /// The iterator returns an error when a run cannot be read.
+    pub fn into_sorted_iter(mut self) -> io::Result<SortedDetections> {
+        self.records.sort();
+        let mut sources: Vec<Box<dyn Iterator<Item = io::Result<SortRecord>>>> = vec![];
+        for path in &self.runs {
+            let reader = BufReader::new(File::open(path)?);
+            let records = reader.lines().filter_map(|line| match line {
+                Ok(line) => SortRecord::from_line(&line).map(Ok),
+                Err(e) => Some(Err(e)),
+            });
+            sources.push(Box::new(records));
+        }
+        // Equal records are returned in the order they were pushed, from the oldest run to the memory,
+        // so the order does not depend on when the runs were spilled.
+        sources.push(Box::new(
+            std::mem::take(&mut self.records).into_iter().map(Ok),
+        ));
+        let mut heap = BinaryHeap::new();
+        for (i, source) in sources.iter_mut().enumerate() {
+            if let Some(record) = source.next() {
+                heap.push(Reverse((record?, i)));
+            }
+        }
+        Ok(SortedDetections {
+            sources,
+            heap,
+            _temp_dir: self.temp_dir,
+        })
+    }
+}
+
+pub struct SortedDetections {
+    sources: Vec<Box<dyn Iterator<Item = io::Result<SortRecord>>>>,
+    heap: BinaryHeap<Reverse<(SortRecord, usize)>>,
+    // Keep the temp directory alive until all runs have been read.
+    _temp_dir: Option<TempDir>,
+}
+
+impl Iterator for SortedDetections {
+    type Item = io::Result<(Option<usize>, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Reverse((record, i)) = self.heap.pop()?;
+            match self.sources[i].next() {
+                Some(Ok(next)) => self.heap.push(Reverse((next, i))),
+                Some(Err(e)) => return Some(Err(e)),
+                None => {}
+            }
+            if let Ok(event) = serde_json::from_str(&record.event) {
+                return Some(Ok((record.rule_index, event)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sorted_times(sorter: DetectionSorter) -> Vec<(Option<usize>, String)> {
+        sorter
+            .into_sorted_iter()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .map(|(i, v)| (i, v["eventTime"].as_str().unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_in_memory() {
+        let mut sorter = DetectionSorter::new(SORT_MEMORY_BUDGET);
+        sorter
+            .push(Some(1), "B", &json!({"eventTime": "2024-08-18T13:00:00Z"}))
+            .unwrap();
+        sorter
+            .push(Some(0), "A", &json!({"eventTime": "2024-08-18T13:00:00Z"}))
+            .unwrap();
+        sorter
+            .push(None, "", &json!({"eventTime": "2024-08-18T12:00:00Z"}))
+            .unwrap();
+        let result = sorted_times(sorter);
+        assert_eq!(
+            result,
+            vec![
+                (None, "2024-08-18T12:00:00Z".to_string()),
+                (Some(0), "2024-08-18T13:00:00Z".to_string()),
+                (Some(1), "2024-08-18T13:00:00Z".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_with_spilled_runs() {
+        let mut sorter = DetectionSorter::new(1);
+        for hour in [5, 1, 4, 2, 3] {
+            let time = format!("2024-08-18T0{}:00:00Z", hour);
+            let rule_index = (hour % 2 == 0).then_some(0);
+            sorter
+                .push(rule_index, "A", &json!({ "eventTime": time }))
+                .unwrap();
+        }
+        assert_eq!(sorter.runs.len(), 5);
+        let times: Vec<String> = sorted_times(sorter).into_iter().map(|(_, t)| t).collect();
+        assert_eq!(
+            times,
+            vec![
+                "2024-08-18T01:00:00Z",
+                "2024-08-18T02:00:00Z",
+                "2024-08-18T03:00:00Z",
+                "2024-08-18T04:00:00Z",
+                "2024-08-18T05:00:00Z",
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::option::cli::InputOption;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileState {
+    size: u64,
+    mtime: u64,
+    sha256: String,
+}
+
+/// The processed log files saved with --state for incremental scanning.
+/// The seen eventIDs are not saved, so duplicate events are only removed within a run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    // The events of the recorded files were filtered with these options.
+    #[serde(default)]
+    filters: String,
+    files: BTreeMap<String, FileState>,
+    #[serde(skip)]
+    pub skipped_files: usize,
+}
+
+impl ScanState {
+    /// Loads the state file. A state file that does not exist yet is an empty state.
+    /// The state must have been saved with the same filter options (see `filter_options`).
+    pub fn load(path: &Path, filters: &str) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(ScanState {
+                filters: filters.to_string(),
+                ..Default::default()
+            });
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let state: ScanState = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid state file {}: {}", path.display(), e))?;
+        if state.filters != filters {
+            return Err(format!(
+                "The filtering options are different from the ones used when {} was saved. Please specify the same options or another state file.",
+                path.display()
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Saves the state through a temporary file so an interrupted run does not break it.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Returns the new state of the file if its size or modification time differs from the state,
+    /// or None if it can be skipped. The hash is set by `check_contents` after reading the file.
+    pub fn check(&mut self, path: &str) -> io::Result<Option<FileState>> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        if self
+            .files
+            .get(path)
+            .is_some_and(|f| f.size == metadata.len() && f.mtime == mtime)
+        {
+            self.skipped_files += 1;
+            return Ok(None);
+        }
+        Ok(Some(FileState {
+            size: metadata.len(),
+            mtime,
+            sha256: String::new(),
+        }))
+    }
+
+    /// Hashes the contents of a file returned by `check`.
+    /// Returns None if the contents are the same as the state (the file was only touched).
+    pub fn check_contents(
+        &mut self,
+        path: &str,
+        mut state: FileState,
+        contents: &[u8],
+    ) -> Option<FileState> {
+        state.sha256 = Sha256::digest(contents)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if self
+            .files
+            .get(path)
+            .is_some_and(|f| f.sha256 == state.sha256)
+        {
+            // the events were already processed
+            self.files.insert(path.to_string(), state);
+            self.skipped_files += 1;
+            return None;
+        }
+        Some(state)
+    }
+
+    /// Records a processed file.
+    pub fn record(&mut self, path: &str, state: FileState) {
+        self.files.insert(path.to_string(), state);
+    }
+}
+
+/// Describes the options that filter the events before the detection.
+/// Files scanned with other filters have to be scanned again, so the state is only used with the same ones.
+pub fn filter_options(input_opt: &InputOption) -> String {
+    format!(
+        "{:?}",
+        (
+            &input_opt.include_field,
+            &input_opt.exclude_field,
+            &input_opt.where_expr,
+            &input_opt.event_category,
+            input_opt.read_only,
+            input_opt.write_only,
+            input_opt.keep_duplicates,
+            // the start time of --time-offset changes on each run
+            &input_opt.time_opt.timeline_start,
+            &input_opt.time_opt.timeline_end,
+            &input_opt.time_opt.time_offset,
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks a file like the scan does.
+    fn check(state: &mut ScanState, path: &str) -> Option<FileState> {
+        let file_state = state.check(path).unwrap()?;
+        state.check_contents(path, file_state, &fs::read(path).unwrap())
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log.json");
+        let log_path = log.to_str().unwrap();
+        fs::write(&log, r#"{"Records":[]}"#).unwrap();
+        let state_path = dir.path().join("state.json");
+
+        let mut state = ScanState::load(&state_path, "").unwrap();
+        let file_state = check(&mut state, log_path).unwrap();
+        state.record(log_path, file_state);
+        state.save(&state_path).unwrap();
+
+        let mut state = ScanState::load(&state_path, "").unwrap();
+        assert!(check(&mut state, log_path).is_none());
+    }
+
+    #[test]
+    fn test_unchanged_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log.json");
+        let log_path = log.to_str().unwrap();
+        fs::write(&log, r#"{"Records":[]}"#).unwrap();
+
+        let mut state = ScanState::default();
+        let file_state = check(&mut state, log_path).unwrap();
+        state.record(log_path, file_state);
+        assert!(check(&mut state, log_path).is_none());
+        // a changed file is scanned again
+        fs::write(&log, r#"{"Records":[{}]}"#).unwrap();
+        assert!(check(&mut state, log_path).is_some());
+        assert_eq!(state.skipped_files, 1);
+    }
+
+    #[test]
+    fn test_touched_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log.json");
+        let log_path = log.to_str().unwrap();
+        fs::write(&log, r#"{"Records":[]}"#).unwrap();
+
+        let mut state = ScanState::default();
+        let mut file_state = check(&mut state, log_path).unwrap();
+        // the modification time differs but the contents are the same
+        file_state.mtime = 0;
+        state.record(log_path, file_state);
+        assert!(check(&mut state, log_path).is_none());
+        assert_eq!(state.skipped_files, 1);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state.json");
+        ScanState::load(&state_path, "filters")
+            .unwrap()
+            .save(&state_path)
+            .unwrap();
+        assert!(ScanState::load(&state_path, "filters").is_ok());
+        assert!(ScanState::load(&state_path, "other filters").is_err());
+
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        assert!(ScanState::load(&dir.path().join("broken.json"), "").is_err());
+    }
+}
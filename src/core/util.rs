@@ -1,27 +1,18 @@
 use crate::core::color::SuzakuColor::Green;
 use bytesize::ByteSize;
-use csv::Writer;
+use serde_json::Value;
 use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
-pub fn get_writer(output: &Option<PathBuf>) -> Writer<Box<dyn Write>> {
-    let wtr: Writer<Box<dyn io::Write>> = if let Some(output) = output {
-        Writer::from_writer(Box::new(fs::File::create(output).unwrap()))
+/// Opens an output file, appending to the existing content with `append`.
+pub fn open_output_file(path: &Path, append: bool) -> io::Result<File> {
+    if append {
+        fs::OpenOptions::new().create(true).append(true).open(path)
     } else {
-        Writer::from_writer(Box::new(io::stdout()))
-    };
-    wtr
-}
-
-pub fn get_json_writer(output: &Option<PathBuf>) -> BufWriter<Box<dyn Write>> {
-    if let Some(output) = output {
-        let file = File::create(output).expect("Failed to create file");
-        BufWriter::new(Box::new(file))
-    } else {
-        BufWriter::new(Box::new(std::io::stdout()))
+        File::create(path)
     }
 }
 
@@ -67,3 +58,23 @@ pub fn output_path_info(no_color: bool, output_paths: &[PathBuf]) {
     }
     println!();
 }
+
+/// Gets a nested value from a CloudTrail event with a dotted path (ex: userIdentity.arn).
+pub fn get_json_value<'a>(json: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(json, |v, k| v.get(k))
+}
+
+pub fn get_json_str<'a>(json: &'a Value, key: &str) -> Option<&'a str> {
+    get_json_value(json, key).and_then(|v| v.as_str())
+}
+
+pub const INSIGHT_CATEGORY: &str = "Insight";
+
+/// The eventCategory of a CloudTrail event. Old events without the field are management events.
+pub fn event_category(json: &Value) -> &str {
+    match get_json_str(json, "eventCategory") {
+        Some(category) => category,
+        None if json.get("insightDetails").is_some() => INSIGHT_CATEGORY,
+        None => "Management",
+    }
+}
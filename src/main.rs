@@ -1,15 +1,25 @@
-use chrono::Local;
+use chrono::{Local, TimeDelta, Utc};
 use clap::{CommandFactory, Parser};
-use cmd::aws_detect::aws_detect;
+use cmd::Status;
+use cmd::aws_detect::{OutputType, aws_detect};
 use cmd::aws_metrics::aws_metrics;
-use cmd::aws_summary::aws_summary;
+use cmd::aws_summary::{aws_summary, summary_csv_path};
+use cmd::pivot_keywords::pivot_keywords_list;
 use cmd::update::start_update_rules;
+use core::checkpoint::is_resumable;
 use core::color::SuzakuColor::Green;
 use core::util::{check_path_exists, p};
 use libmimalloc_sys::mi_stats_print_out;
 use mimalloc::MiMalloc;
-use option::cli::Commands::{AwsCtMetrics, AwsCtSummary, AwsCtTimeline, UpdateRules};
+use option::cli::Commands::{
+    AwsCtMetrics, AwsCtSummary, AwsCtTimeline, PivotKeywordsList, UpdateRules,
+};
 use option::cli::{Cli, RELEASE_NAME, VERSION};
+use option::timefiler::parse_offset;
+use output::pivot;
+use output::syslog::SyslogTarget;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::ptr::null_mut;
 use std::time::Instant;
 use std::{env, fs};
@@ -17,11 +27,12 @@ use std::{env, fs};
 mod cmd;
 mod core;
 mod option;
+mod output;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-fn main() {
+fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
     if args.len() == 1
         || args.len() == 2
@@ -29,17 +40,62 @@ fn main() {
     {
         display_logo(false, false, false, true);
         Cli::command().print_help().unwrap();
-        return;
+        return ExitCode::SUCCESS;
     }
     let start = Instant::now();
-    let cmd = &Cli::parse().cmd;
+    let mut cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            // --help and --version are not errors
+            let status = if e.use_stderr() {
+                Status::Error
+            } else {
+                Status::Clean
+            };
+            e.print().ok();
+            return status.into();
+        }
+    };
+    // --time-offset is resolved once, so the start time does not move during a scan
+    let now = Utc::now();
+    match &mut cli.cmd {
+        AwsCtTimeline { options, .. } => options.input_opt.time_opt.resolve_offset(now),
+        AwsCtMetrics { input_opt, .. }
+        | AwsCtSummary { input_opt, .. }
+        | PivotKeywordsList { input_opt, .. } => input_opt.time_opt.resolve_offset(now),
+        UpdateRules { .. } => {}
+    }
+    let cmd = &cli.cmd;
     let no_color = match cmd {
         AwsCtTimeline { common_opt, .. } => common_opt.no_color,
         AwsCtMetrics { common_opt, .. } => common_opt.no_color,
         AwsCtSummary { common_opt, .. } => common_opt.no_color,
+        PivotKeywordsList { common_opt, .. } => common_opt.no_color,
         UpdateRules { common_opt } => common_opt.no_color,
     };
-    match cmd {
+    let time_opt = match cmd {
+        AwsCtTimeline { options, .. } => Some(&options.input_opt.time_opt),
+        AwsCtMetrics { input_opt, .. }
+        | AwsCtSummary { input_opt, .. }
+        | PivotKeywordsList { input_opt, .. } => Some(&input_opt.time_opt),
+        UpdateRules { .. } => None,
+    };
+    if let Some(time_opt) = time_opt {
+        if let (Some(start), Some(end)) = (time_opt.timeline_start, time_opt.timeline_end) {
+            if start > end {
+                p(
+                    None,
+                    &format!(
+                        "Invalid time range: --timeline-start ({}) is after --timeline-end ({}).",
+                        start, end
+                    ),
+                    true,
+                );
+                return Status::Error.into();
+            }
+        }
+    }
+    let status = match cmd {
         AwsCtTimeline {
             options,
             common_opt,
@@ -49,17 +105,38 @@ fn main() {
             let dir = &options.input_opt.directory;
             let file = &options.input_opt.filepath;
             if !check_path_exists(file.clone(), dir.clone()) {
-                return;
+                return Status::Error.into();
             }
-            if let Some(output) = &options.output {
-                if !options.clobber && output.exists() {
-                    let msg = format!(
-                        "The file {} already exists. Please specify a different filename or add the -C, --clobber option to overwrite.",
-                        output.display()
-                    );
-                    p(None, msg.as_str(), true);
-                    return;
-                }
+            // the syslog file is created at startup, so it exists when resuming
+            let syslog_file = match options.syslog.as_deref().map(SyslogTarget::parse) {
+                Some(SyslogTarget::File(path)) if !options.resume => Some(path),
+                _ => None,
+            };
+            let mut outputs: Vec<PathBuf> = vec![];
+            // the output files are expected to exist with --append and --resume
+            if let (false, false, Some(output)) = (options.append, options.resume, &options.output)
+            {
+                let output_type =
+                    OutputType::from_u8(options.output_type).unwrap_or(OutputType::Csv);
+                outputs.extend(output_type.output_paths(output));
+            }
+            outputs.extend(
+                [
+                    &options.html_report,
+                    &options.summary_output,
+                    &options.stix,
+                    &syslog_file,
+                    &options.redact_opt.redact_map,
+                ]
+                .into_iter()
+                .flatten()
+                .cloned(),
+            );
+            if let Some(path) = &options.pivot_keywords {
+                outputs.extend(pivot_output_paths(path));
+            }
+            if !options.clobber && !check_clobber(&outputs) {
+                return Status::Error.into();
             }
             if !options.rules.exists() {
                 p(
@@ -67,15 +144,44 @@ fn main() {
                     &format!("Rule file or directory does not exist: {:?}", options.rules),
                     true,
                 );
-                return;
+                return Status::Error.into();
             }
-            if options.raw_output && options.output_type == 1 {
+            if options.raw_output && matches!(options.output_type, 1 | 6 | 9..=11) {
                 p(
                     None,
-                    "--raw-output option is only available in JSON formats. Please specify an output type of 2-5.",
+                    "--raw-output option is only available in JSON formats. Please specify an output type of 2-5, 7 or 8.",
                     true,
                 );
-                return;
+                return Status::Error.into();
+            }
+            if options.append && !matches!(options.output_type, 1 | 3 | 5) {
+                p(
+                    None,
+                    "--append option is only available in CSV and JSONL formats. Please specify an output type of 1, 3 or 5.",
+                    true,
+                );
+                return Status::Error.into();
+            }
+            if options.checkpoint && !is_resumable(options) {
+                p(
+                    None,
+                    "--checkpoint option is not available with --http-url, --syslog to a network target, or --redact without --redact-key when results are written while scanning (--no-sort or --syslog). With --no-sort, it is only available in CSV, JSON and JSONL formats (output type 1-5) without --html-report, --stix and --pivot-keywords.",
+                    true,
+                );
+                return Status::Error.into();
+            }
+            if let Some(window) = &options.aggregate_window {
+                if parse_offset(window).is_none_or(|w| w <= TimeDelta::zero()) {
+                    p(
+                        None,
+                        &format!(
+                            "Invalid aggregate window: {}. Please specify a duration (ex: 30s, 10m, 1h).",
+                            window
+                        ),
+                        true,
+                    );
+                    return Status::Error.into();
+                }
             }
             if options.min_level != "informational"
                 && options.min_level != "info"
@@ -94,9 +200,9 @@ fn main() {
                     ),
                     true,
                 );
-                return;
+                return Status::Error.into();
             }
-            aws_detect(options, common_opt);
+            aws_detect(options, common_opt)
         }
         AwsCtMetrics {
             input_opt,
@@ -109,23 +215,31 @@ fn main() {
             let file = &input_opt.filepath;
             let field_name = field_name.as_ref();
             if !check_path_exists(file.clone(), dir.clone()) {
-                return;
+                return Status::Error.into();
             }
-            aws_metrics(input_opt, field_name, output, no_color);
+            aws_metrics(input_opt, field_name, output, no_color)
         }
         AwsCtSummary {
             input_opt,
             include_sts,
             output,
+            clobber,
             hide_descriptions,
             geo_ip,
+            time_format_opt,
+            redact_opt,
             common_opt,
         } => {
             display_logo(common_opt.quiet, no_color, true, false);
             let dir = &input_opt.directory;
             let file = &input_opt.filepath;
             if !check_path_exists(file.clone(), dir.clone()) {
-                return;
+                return Status::Error.into();
+            }
+            let mut outputs = vec![summary_csv_path(output)];
+            outputs.extend(redact_opt.redact_map.iter().cloned());
+            if !clobber && !check_clobber(&outputs) {
+                return Status::Error.into();
             }
             aws_summary(
                 input_opt,
@@ -134,13 +248,44 @@ fn main() {
                 include_sts,
                 hide_descriptions,
                 geo_ip,
-            );
+                time_format_opt,
+                redact_opt,
+            )
+        }
+        PivotKeywordsList {
+            rules,
+            input_opt,
+            output,
+            clobber,
+            min_level,
+            common_opt,
+        } => {
+            display_logo(common_opt.quiet, no_color, true, false);
+            let dir = &input_opt.directory;
+            let file = &input_opt.filepath;
+            if !check_path_exists(file.clone(), dir.clone()) {
+                return Status::Error.into();
+            }
+            if let Some(path) = output {
+                if !clobber && !check_clobber(&pivot_output_paths(path)) {
+                    return Status::Error.into();
+                }
+            }
+            if !rules.exists() {
+                p(
+                    None,
+                    &format!("Rule file or directory does not exist: {:?}", rules),
+                    true,
+                );
+                return Status::Error.into();
+            }
+            pivot_keywords_list(rules, input_opt, output, min_level, common_opt)
         }
         UpdateRules { common_opt } => {
             display_logo(common_opt.quiet, no_color, true, false);
-            start_update_rules(no_color);
+            start_update_rules(no_color)
         }
-    }
+    };
 
     let duration = start.elapsed();
     let hours = duration.as_secs() / 3600;
@@ -156,6 +301,7 @@ fn main() {
         AwsCtTimeline { common_opt, .. } => common_opt.debug,
         AwsCtMetrics { common_opt, .. } => common_opt.debug,
         AwsCtSummary { common_opt, .. } => common_opt.debug,
+        PivotKeywordsList { common_opt, .. } => common_opt.debug,
         UpdateRules { common_opt } => common_opt.debug,
     };
 
@@ -181,6 +327,29 @@ fn main() {
             mi_stats_print_out(None, null_mut());
         }
     }
+    status.into()
+}
+
+// Returns false if any of the output files already exists.
+fn check_clobber(outputs: &[PathBuf]) -> bool {
+    for output in outputs {
+        if output.exists() {
+            let msg = format!(
+                "The file {} already exists. Please specify a different filename or add the -C, --clobber option to overwrite.",
+                output.display()
+            );
+            p(None, msg.as_str(), true);
+            return false;
+        }
+    }
+    true
+}
+
+fn pivot_output_paths(base: &Path) -> Vec<PathBuf> {
+    pivot::load_config(pivot::PIVOT_CONFIG_PATH)
+        .iter()
+        .map(|(category, _)| pivot::output_path(base, category))
+        .collect()
 }
 
 fn display_logo(quiet: bool, no_color: bool, time: bool, help: bool) {
@@ -1,3 +1,6 @@
 pub mod cli;
+pub mod fieldfilter;
 pub mod geoip;
+pub mod query;
 pub mod timefiler;
+pub mod timeformat;
@@ -1,3 +1,7 @@
+use crate::option::fieldfilter::{FieldCondition, parse_field_condition};
+use crate::option::query::{WhereExpr, parse_where};
+use crate::option::timefiler::{TimeOffset, parse_end_time, parse_start_time, parse_time_offset};
+use chrono::{DateTime, Utc};
 use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -6,6 +10,16 @@ use const_format::concatcp;
 pub const RELEASE_NAME: &str = "Dev Build";
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const FULL_VERSION: &str = concatcp!(VERSION, " ", RELEASE_NAME);
+const LEVELS: [&str; 8] = [
+    "informational",
+    "info",
+    "low",
+    "medium",
+    "med",
+    "high",
+    "critical",
+    "crit",
+];
 
 #[derive(Parser)]
 #[command(name = "suzaku")]
@@ -38,17 +52,107 @@ pub struct CommonOptions {
 
 #[derive(Args, Clone, Debug, Default)]
 pub struct TimeOption {
-    /// Start time of the events to load (ex: "2022-02-22T23:59:59Z)
-    #[arg(help_heading = Some("Filtering"), long = "timeline-start", value_name = "DATE")]
-    pub timeline_start: Option<String>,
+    /// Start time of the events to load (ex: 2022-02-22, "2022-02-22 23:59:59", 2022-02-22T23:59:59+09:00, 1645574399)
+    #[arg(help_heading = Some("Filtering"), long = "timeline-start", value_name = "DATE", value_parser = parse_start_time)]
+    pub timeline_start: Option<DateTime<Utc>>,
 
-    /// End time of the events to load (ex: "2020-02-22T00:00:00Z")
-    #[arg(help_heading = Some("Filtering"), long = "timeline-end", value_name = "DATE")]
-    pub timeline_end: Option<String>,
+    /// End time of the events to load (ex: 2020-02-22, "2020-02-22 00:00:00", 2020-02-22T00:00:00Z)
+    #[arg(help_heading = Some("Filtering"), long = "timeline-end", value_name = "DATE", value_parser = parse_end_time)]
+    pub timeline_end: Option<DateTime<Utc>>,
 
-    /// Scan recent events based on an offset (ex: 1y, 3M, 30d, 24h, 30m)
-    #[arg(help_heading = Some("Filtering"), long = "time-offset", value_name = "OFFSET", conflicts_with = "timeline_start")]
-    pub time_offset: Option<String>,
+    /// Scan recent events based on an offset (ex: 1y, 3M, 30d, 1d12h, 30m)
+    #[arg(help_heading = Some("Filtering"), long = "time-offset", value_name = "OFFSET", conflicts_with = "timeline_start", value_parser = parse_time_offset)]
+    pub time_offset: Option<TimeOffset>,
+
+    /// The start time of --time-offset, resolved once at startup
+    #[arg(skip)]
+    pub offset_start: Option<DateTime<Utc>>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+#[clap(group(ArgGroup::new("time_format").args(["european_time", "iso_8601", "rfc_2822", "rfc_3339", "us_time"]).multiple(false)))]
+pub struct TimeFormatOptions {
+    /// Output timestamps in a specific timezone (ex: Asia/Tokyo, America/New_York, local)
+    #[arg(help_heading = Some("Time Format"), long = "timezone", value_name = "TIMEZONE", conflicts_with = "utc")]
+    pub timezone: Option<String>,
+
+    /// Output timestamp in European time format (ex: 22-02-2022 22:00:00 +02:00)
+    #[arg(help_heading = Some("Time Format"), long = "European-time")]
+    pub european_time: bool,
+
+    /// Output timestamp in ISO-8601 format (ex: 2022-02-22T10:10:10Z)
+    #[arg(help_heading = Some("Time Format"), short = 'O', long = "ISO-8601")]
+    pub iso_8601: bool,
+
+    /// Output timestamp in RFC 2822 format (ex: Fri, 22 Feb 2022 22:00:00 -0600)
+    #[arg(help_heading = Some("Time Format"), long = "RFC-2822")]
+    pub rfc_2822: bool,
+
+    /// Output timestamp in RFC 3339 format (ex: 2022-02-22 22:00:00-06:00)
+    #[arg(help_heading = Some("Time Format"), long = "RFC-3339")]
+    pub rfc_3339: bool,
+
+    /// Output timestamp in US time format (ex: 02-22-2022 10:00:00 PM -06:00)
+    #[arg(help_heading = Some("Time Format"), long = "US-time")]
+    pub us_time: bool,
+
+    /// Output time in UTC format (default)
+    #[arg(help_heading = Some("Time Format"), short = 'U', long = "UTC")]
+    pub utc: bool,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct HttpOutputOptions {
+    /// Send detections to an HTTP endpoint (ex: https://splunk:8088/services/collector/event)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-url", value_name = "URL")]
+    pub http_url: Option<String>,
+
+    /// HTTP payload format: hec (Splunk HTTP Event Collector) or webhook (JSON array) (default: webhook)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-format", value_name = "FORMAT", default_value = "webhook", hide_default_value = true, value_parser = ["hec", "webhook"], requires = "http_url")]
+    pub http_format: String,
+
+    /// Token for authentication (sent as "Splunk <TOKEN>" for hec and "Bearer <TOKEN>" for webhook)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-token", value_name = "TOKEN", env = "SUZAKU_HTTP_TOKEN", hide_env_values = true, requires = "http_url")]
+    pub http_token: Option<String>,
+
+    /// Number of detections to send in one request (default: 100)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-batch-size", value_name = "NUMBER", default_value = "100", hide_default_value = true, value_parser = clap::value_parser!(u32).range(1..), requires = "http_url")]
+    pub http_batch_size: u32,
+
+    /// Number of retries when a request fails (default: 3)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-retries", value_name = "NUMBER", default_value = "3", hide_default_value = true, requires = "http_url")]
+    pub http_retries: u32,
+
+    /// PEM file of the CA certificates to verify the endpoint
+    #[arg(help_heading = Some("HTTP Output"), long = "http-ca-cert", value_name = "FILE", requires = "http_url")]
+    pub http_ca_cert: Option<PathBuf>,
+
+    /// Do not verify the TLS certificate of the endpoint
+    #[arg(help_heading = Some("HTTP Output"), long = "http-insecure", requires = "http_url")]
+    pub http_insecure: bool,
+
+    /// File to save detections that could not be sent (default: ./suzaku-http-spool.jsonl)
+    #[arg(help_heading = Some("HTTP Output"), long = "http-spool", value_name = "FILE", default_value = "suzaku-http-spool.jsonl", hide_default_value = true, requires = "http_url")]
+    pub http_spool: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct RedactOptions {
+    /// Replace account IDs, IP addresses, user names, ARNs and access keys with pseudonyms
+    #[arg(help_heading = Some("Redaction"), long = "redact")]
+    pub redact: bool,
+
+    /// File of the fields to redact (default: ./config/redact_fields.txt)
+    #[arg(help_heading = Some("Redaction"), long = "redact-fields", value_name = "FILE", default_value = "config/redact_fields.txt", hide_default_value = true, requires = "redact")]
+    pub redact_fields: PathBuf,
+
+    /// Secret key for the pseudonyms. Use the same key to get the same pseudonyms across scans (default: random)
+    #[arg(help_heading = Some("Redaction"), long = "redact-key", value_name = "KEY", env = "SUZAKU_REDACT_KEY", hide_env_values = true, requires = "redact")]
+    pub redact_key: Option<String>,
+
+    /// Save the pseudonyms and original values to a CSV file (keep it private)
+    #[arg(help_heading = Some("Redaction"), long = "redact-map", value_name = "FILE", requires = "redact")]
+    pub redact_map: Option<PathBuf>,
 }
 
 #[derive(Args, Clone, Debug, Default)]
@@ -62,6 +166,34 @@ pub struct InputOption {
     #[arg(help_heading = Some("Input"), short = 'f', long = "file", value_name = "FILE", conflicts_with_all = ["directory"])]
     pub filepath: Option<PathBuf>,
 
+    /// Do not remove duplicate events with the same eventID (ex: organization and account trails)
+    #[arg(help_heading = Some("Input"), long = "keep-duplicates")]
+    pub keep_duplicates: bool,
+
+    /// Only scan events whose field matches (ex: awsRegion=us-east-1, userIdentity.arn=*:user/alice, eventName=~^Delete)
+    #[arg(help_heading = Some("Filtering"), long = "include-field", value_name = "FIELD=VALUE", value_parser = parse_field_condition)]
+    pub include_field: Vec<FieldCondition>,
+
+    /// Do not scan events whose field matches (ex: eventSource=s3.amazonaws.com)
+    #[arg(help_heading = Some("Filtering"), long = "exclude-field", value_name = "FIELD=VALUE", value_parser = parse_field_condition)]
+    pub exclude_field: Vec<FieldCondition>,
+
+    /// Only scan events matching an expression (ex: "eventName startswith Create and errorCode is null and sourceIPAddress not in 10.0.0.0/8")
+    #[arg(help_heading = Some("Filtering"), long = "where", value_name = "EXPR", value_parser = parse_where)]
+    pub where_expr: Option<WhereExpr>,
+
+    /// Only scan events of the categories (Management, Data, Insight)
+    #[arg(help_heading = Some("Filtering"), long = "event-category", value_name = "CATEGORY", value_delimiter = ',', value_parser = ["Management", "Data", "Insight"], ignore_case = true)]
+    pub event_category: Vec<String>,
+
+    /// Only scan read-only events
+    #[arg(help_heading = Some("Filtering"), long = "read-only", conflicts_with = "write_only")]
+    pub read_only: bool,
+
+    /// Only scan events that are not read-only
+    #[arg(help_heading = Some("Filtering"), long = "write-only")]
+    pub write_only: bool,
+
     #[clap(flatten)]
     pub time_opt: TimeOption,
 }
@@ -79,14 +211,82 @@ pub struct AwsCtTimelineOptions {
     #[arg(help_heading = Some("Output"), short, long, value_name = "FILE")]
     pub output: Option<PathBuf>,
 
-    /// Output type 1: CSV (default), 2: JSON, 3: JSONL, 4: CSV & JSON, 5: CSV & JSONL
-    #[arg(help_heading = Some("Output"), short = 't', long = "output-type", requires = "output", value_parser = clap::value_parser!(u8).range(1..=5), default_value = "1")]
+    /// Output type 1: CSV (default), 2: JSON, 3: JSONL, 4: CSV & JSON, 5: CSV & JSONL, 6: SQLite, 7: Elasticsearch bulk (ECS NDJSON), 8: OCSF (JSONL), 9: Timesketch CSV, 10: Timesketch JSONL, 11: Parquet
+    #[arg(help_heading = Some("Output"), short = 't', long = "output-type", requires = "output", value_parser = clap::value_parser!(u8).range(1..=11), default_value = "1")]
     pub output_type: u8,
 
     /// Overwrite files when saving
-    #[arg(help_heading = Some("Output"), short='C', long = "clobber", requires = "output", display_order = 1)]
+    #[arg(help_heading = Some("Output"), short='C', long = "clobber", display_order = 1)]
     pub clobber: bool,
 
+    /// Output all events including events without detections (rule columns are empty for them)
+    #[arg(help_heading = Some("Output"), short = 'A', long = "all-events", requires = "output")]
+    pub all_events: bool,
+
+    /// Save a detection report to a HTML file
+    #[arg(help_heading = Some("Output"), long = "html-report", value_name = "FILE")]
+    pub html_report: Option<PathBuf>,
+
+    /// Save the results summary to a JSON file
+    #[arg(help_heading = Some("Output"), long = "summary-output", value_name = "FILE")]
+    pub summary_output: Option<PathBuf>,
+
+    /// Save detections and observables (IP addresses, user accounts, access keys, user agents) to a STIX 2.1 bundle JSON file
+    #[arg(help_heading = Some("Output"), long = "stix", value_name = "FILE")]
+    pub stix: Option<PathBuf>,
+
+    /// Send detections as syslog (RFC 5424) to udp://HOST:PORT or tcp://HOST:PORT, or save them to a file
+    #[arg(help_heading = Some("Output"), long = "syslog", value_name = "TARGET")]
+    pub syslog: Option<String>,
+
+    /// Syslog payload format: cef or leef (default: cef)
+    #[arg(help_heading = Some("Output"), long = "syslog-format", value_name = "FORMAT", default_value = "cef", hide_default_value = true, value_parser = ["cef", "leef"], requires = "syslog")]
+    pub syslog_format: String,
+
+    /// Save unique IP addresses, users, access keys and user agents of detections to text files per category (ex: -p pivot.txt -> pivot-Users.txt)
+    #[arg(help_heading = Some("Output"), short = 'p', long = "pivot-keywords", value_name = "FILE")]
+    pub pivot_keywords: Option<PathBuf>,
+
+    /// Minimum level of detections for pivot keywords (default: critical)
+    #[arg(help_heading = Some("Output"), long = "pivot-min-level", default_value = "critical", hide_default_value = true, value_name = "LEVEL", value_parser = LEVELS, requires = "pivot_keywords")]
+    pub pivot_min_level: String,
+
+    /// Collapse detections of the same rule and key fields within a time window into one row with the count (ex: 30s, 10m, 1h)
+    #[arg(help_heading = Some("Output"), long = "aggregate-window", value_name = "DURATION", conflicts_with_all = ["no_sort", "all_events"])]
+    pub aggregate_window: Option<String>,
+
+    /// Fields to group detections by when aggregating (default: userIdentity.arn,sourceIPAddress)
+    #[arg(help_heading = Some("Output"), long = "aggregate-keys", value_name = "FIELDS", value_delimiter = ',', default_value = "userIdentity.arn,sourceIPAddress", hide_default_value = true, requires = "aggregate_window")]
+    pub aggregate_keys: Vec<String>,
+
+    /// Do not sort results by timestamp (faster and uses less memory)
+    #[arg(help_heading = Some("Output"), long = "no-sort")]
+    pub no_sort: bool,
+
+    /// Save processed log files to a state file and only scan new or changed files in the next runs (duplicate events are only removed within a run)
+    #[arg(help_heading = Some("Input"), long = "state", value_name = "FILE", conflicts_with = "filepath")]
+    pub state: Option<PathBuf>,
+
+    /// Append results to existing CSV/JSONL output files instead of overwriting them
+    #[arg(help_heading = Some("Output"), long = "append", requires = "output")]
+    pub append: bool,
+
+    /// Keep running and scan new log files as they arrive in the directory (stop with Ctrl-C)
+    #[arg(help_heading = Some("Input"), long = "follow", conflicts_with_all = ["filepath", "aggregate_window"])]
+    pub follow: bool,
+
+    /// Interval in seconds to poll the directory for new log files with --follow (default: 10)
+    #[arg(help_heading = Some("Input"), long = "poll-interval", value_name = "SECONDS", default_value = "10", hide_default_value = true, value_parser = clap::value_parser!(u64).range(1..), requires = "follow")]
+    pub poll_interval: u64,
+
+    /// Save the scan progress to <OUTPUT>.checkpoint every 5 minutes and on Ctrl-C to continue an interrupted scan with --resume
+    #[arg(help_heading = Some("Input"), long = "checkpoint", requires = "output", conflicts_with_all = ["filepath", "follow", "state", "append"])]
+    pub checkpoint: bool,
+
+    /// Continue an interrupted scan from its checkpoint (the same command with --checkpoint)
+    #[arg(help_heading = Some("Input"), long = "resume", requires = "checkpoint")]
+    pub resume: bool,
+
     /// Disable event frequency timeline (terminal needs to support Unicode)
     #[arg(help_heading = Some("Display Settings"), short = 'T', long = "no-frequency-timeline", display_order = 3)]
     pub no_frequency: bool,
@@ -99,13 +299,26 @@ pub struct AwsCtTimelineOptions {
     #[arg(help_heading = Some("Output"), short = 'G', long = "geo-ip", value_name = "MAXMIND-DB-DIR", display_order = 2)]
     pub geo_ip: Option<PathBuf>,
 
-    /// Output the original JSON logs (only available in JSON formats)
+    /// Output the original JSON logs (only available in JSON, Elasticsearch and OCSF formats)
     #[arg(help_heading = Some("Output"), short = 'R', long = "raw-output")]
     pub raw_output: bool,
 
     /// Minimum level for rules to load (default: informational)
     #[arg(help_heading = Some("Output"), short = 'm', long = "min-level", default_value = "informational", hide_default_value = true, value_name = "LEVEL", display_order = 3)]
     pub min_level: String,
+
+    /// Exit with code 2 if there are detections at or above this level (for CI jobs)
+    #[arg(help_heading = Some("Output"), long = "fail-on-level", value_name = "LEVEL", value_parser = LEVELS)]
+    pub fail_on_level: Option<String>,
+
+    #[clap(flatten)]
+    pub time_format_opt: TimeFormatOptions,
+
+    #[clap(flatten)]
+    pub http_opt: HttpOutputOptions,
+
+    #[clap(flatten)]
+    pub redact_opt: RedactOptions,
 }
 
 #[derive(Subcommand)]
@@ -120,7 +333,7 @@ pub enum Commands {
     /// Creates an AWS CloudTrail DFIR timeline
     AwsCtTimeline {
         #[clap(flatten)]
-        options: AwsCtTimelineOptions,
+        options: Box<AwsCtTimelineOptions>,
 
         #[clap(flatten)]
         common_opt: CommonOptions,
@@ -176,6 +389,10 @@ pub enum Commands {
         #[arg(help_heading = Some("Output"), short, long, value_name = "FILE", required = true)]
         output: PathBuf,
 
+        /// Overwrite files when saving
+        #[arg(help_heading = Some("Output"), short = 'C', long = "clobber")]
+        clobber: bool,
+
         /// Hide description of the commonly abused API calls
         #[arg(help_heading = Some("Output"), short = 'D', long = "hide-descriptions")]
         hide_descriptions: bool,
@@ -184,6 +401,44 @@ pub enum Commands {
         #[arg(help_heading = Some("Output"), short = 'G', long = "GeoIP", value_name = "MAXMIND-DB-DIR")]
         geo_ip: Option<PathBuf>,
 
+        #[clap(flatten)]
+        time_format_opt: TimeFormatOptions,
+
+        #[clap(flatten)]
+        redact_opt: RedactOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion {version}\n{author-with-newline}\n{usage-heading}\n  suzaku pivot-keywords-list <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates a list of pivot keywords from detections
+    PivotKeywordsList {
+        /// Specify a custom rule directory or file (default: ./rules)
+        #[arg(help_heading = Some("General Options"), short = 'r', long, default_value = "./rules", hide_default_value = true, value_name = "DIR/FILE")]
+        rules: PathBuf,
+
+        #[clap(flatten)]
+        input_opt: InputOption,
+
+        /// Save pivot keywords to text files per category (ex: -o pivot.txt -> pivot-Users.txt)
+        #[arg(help_heading = Some("Output"), short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Overwrite files when saving
+        #[arg(help_heading = Some("Output"), short = 'C', long = "clobber", requires = "output")]
+        clobber: bool,
+
+        /// Minimum level of detections (default: critical)
+        #[arg(help_heading = Some("Output"), short = 'm', long = "min-level", default_value = "critical", hide_default_value = true, value_name = "LEVEL", value_parser = LEVELS)]
+        min_level: String,
+
         #[clap(flatten)]
         common_opt: CommonOptions,
     },
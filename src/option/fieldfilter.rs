@@ -0,0 +1,185 @@
+use crate::core::util::{event_category, get_json_value};
+use crate::option::cli::InputOption;
+use regex::Regex;
+use serde_json::Value;
+
+/// A `field=value` condition of `--include-field`/`--exclude-field`.
+/// The value can contain `*` and `?` wildcards, and `field=~regex` matches with a regular expression.
+#[derive(Clone, Debug)]
+pub struct FieldCondition {
+    field: String,
+    pattern: Regex,
+}
+
+/// Parses a condition for clap (ex: `awsRegion=us-east-1`, `userIdentity.arn=~.*:role/Admin.*`).
+pub fn parse_field_condition(s: &str) -> Result<FieldCondition, String> {
+    let Some((field, value)) = s.split_once('=') else {
+        return Err("the format must be FIELD=VALUE or FIELD=~REGEX".to_string());
+    };
+    let field = field.trim();
+    if field.is_empty() {
+        return Err("the field name is empty".to_string());
+    }
+    let pattern = match value.strip_prefix('~') {
+        Some(regex) => Regex::new(regex).map_err(|e| e.to_string())?,
+        None => {
+            let wildcard: String = value
+                .split_inclusive(['*', '?'])
+                .map(|part| match part.strip_suffix('*') {
+                    Some(s) => format!("{}.*", regex::escape(s)),
+                    None => match part.strip_suffix('?') {
+                        Some(s) => format!("{}.", regex::escape(s)),
+                        None => regex::escape(part),
+                    },
+                })
+                .collect();
+            Regex::new(&format!("^{}$", wildcard)).map_err(|e| e.to_string())?
+        }
+    };
+    Ok(FieldCondition {
+        field: field.to_string(),
+        pattern,
+    })
+}
+
+impl FieldCondition {
+    /// Returns true if the field (or any element of an array field) matches.
+    fn is_match(&self, json: &Value) -> bool {
+        let matches = |v: &Value| match v {
+            Value::String(s) => self.pattern.is_match(s),
+            Value::Number(_) | Value::Bool(_) => self.pattern.is_match(&v.to_string()),
+            _ => false,
+        };
+        match get_json_value(json, &self.field) {
+            Some(Value::Array(values)) => values.iter().any(matches),
+            Some(v) => matches(v),
+            None => false,
+        }
+    }
+}
+
+/// Returns true if the event passes the field filters (including --event-category and --read-only/--write-only).
+/// Include conditions on the same field are OR'ed and those on different fields are AND'ed.
+/// The event is dropped if any exclude condition matches.
+pub fn filter_by_fields(opt: &InputOption, json: &Value) -> bool {
+    if !opt.event_category.is_empty()
+        && !opt
+            .event_category
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(event_category(json)))
+    {
+        return false;
+    }
+    if opt.read_only || opt.write_only {
+        let read_only = match json.get("readOnly") {
+            Some(Value::Bool(b)) => Some(*b),
+            Some(Value::String(s)) => s.parse().ok(),
+            _ => None,
+        };
+        if read_only != Some(opt.read_only) {
+            return false;
+        }
+    }
+    let included = opt.include_field.iter().all(|cond| {
+        opt.include_field
+            .iter()
+            .filter(|c| c.field == cond.field)
+            .any(|c| c.is_match(json))
+    });
+    included && !opt.exclude_field.iter().any(|cond| cond.is_match(json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_by_fields() {
+        let cond = |s: &str| parse_field_condition(s).unwrap();
+        let event = json!({
+            "awsRegion": "us-east-1",
+            "eventSource": "s3.amazonaws.com",
+            "readOnly": true,
+            "userIdentity": {"arn": "arn:aws:iam::123456789012:user/alice"},
+            "resources": ["arn:aws:s3:::bucket-a", "arn:aws:s3:::bucket-b"]
+        });
+        let opt = |include: Vec<&str>, exclude: Vec<&str>| InputOption {
+            include_field: include.into_iter().map(cond).collect(),
+            exclude_field: exclude.into_iter().map(cond).collect(),
+            ..Default::default()
+        };
+        assert!(filter_by_fields(&opt(vec![], vec![]), &event));
+        assert!(filter_by_fields(
+            &opt(vec!["awsRegion=us-east-1"], vec![]),
+            &event
+        ));
+        assert!(!filter_by_fields(
+            &opt(vec!["awsRegion=us-east"], vec![]),
+            &event
+        ));
+        assert!(filter_by_fields(
+            &opt(vec!["awsRegion=us-*-?"], vec![]),
+            &event
+        ));
+        assert!(filter_by_fields(
+            &opt(vec!["readOnly=true"], vec![]),
+            &event
+        ));
+        assert!(filter_by_fields(
+            &opt(vec!["resources=*bucket-b"], vec![]),
+            &event
+        ));
+        assert!(!filter_by_fields(&opt(vec!["errorCode=*"], vec![]), &event));
+        // the same field is OR'ed and different fields are AND'ed
+        let include = vec![
+            "awsRegion=eu-west-1",
+            "awsRegion=us-east-1",
+            "userIdentity.arn=~:user/(alice|bob)$",
+        ];
+        assert!(filter_by_fields(&opt(include.clone(), vec![]), &event));
+        let include = [include, vec!["eventSource=ec2.amazonaws.com"]].concat();
+        assert!(!filter_by_fields(&opt(include, vec![]), &event));
+        assert!(!filter_by_fields(
+            &opt(vec![], vec!["eventSource=s3.*"]),
+            &event
+        ));
+        assert!(filter_by_fields(
+            &opt(vec![], vec!["eventSource=s3"]),
+            &event
+        ));
+
+        let category_opt = |category: &str, read_only: bool, write_only: bool| InputOption {
+            event_category: vec![category.to_string()],
+            read_only,
+            write_only,
+            ..Default::default()
+        };
+        let data_event = json!({"eventCategory": "Data", "readOnly": "true"});
+        assert!(filter_by_fields(
+            &category_opt("data", false, false),
+            &data_event
+        ));
+        assert!(filter_by_fields(
+            &category_opt("Data", true, false),
+            &data_event
+        ));
+        assert!(!filter_by_fields(
+            &category_opt("Data", false, true),
+            &data_event
+        ));
+        // events without eventCategory are management events
+        assert!(filter_by_fields(
+            &category_opt("Management", false, true),
+            &json!({"readOnly": false})
+        ));
+        assert!(!filter_by_fields(
+            &category_opt("Insight", false, false),
+            &event
+        ));
+
+        assert!(parse_field_condition("awsRegion").is_err());
+        assert!(parse_field_condition("=us-east-1").is_err());
+        assert!(parse_field_condition("awsRegion=~(").is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use cidr_utils::cidr::IpCidr;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+/// A `--where` expression (ex: `eventName startswith Create and errorCode is null`).
+///
+/// - Fields are CloudTrail paths (ex: `userIdentity.arn`). Array fields match if any element matches.
+///   Paths go through arrays of objects (ex: `resources.ARN`).
+/// - Comparisons: `==` (or `=`), `!=`, `<`, `<=`, `>`, `>=`, `contains`, `startswith`, `endswith`,
+///   `matches` (or `=~`) with a regular expression, `in` with a value, a CIDR or a list `[a, b]`,
+///   and `is null`/`is not null`.
+/// - Conditions are combined with `and`, `or`, `not` and parentheses.
+#[derive(Clone, Debug)]
+pub struct WhereExpr(Expr);
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    IsNull(String),
+    Cond(String, Op),
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Eq(String),
+    Cmp(Ordering, bool, String),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Matches(Regex),
+    In(Vec<InItem>),
+}
+
+#[derive(Clone, Debug)]
+enum InItem {
+    Value(String),
+    Cidr(IpCidr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Op(String),
+    Str(String),
+    Word(String),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '[' | ']' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    _ => Token::Comma,
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(ch) => value.push(ch),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '=' | '!' | '<' | '>' => {
+                let mut op = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !matches!(ch, '=' | '!' | '<' | '>' | '~') {
+                        break;
+                    }
+                    op.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()[],\"'=!<>".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.tokens.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.tokens.next();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.tokens.next() {
+            Some(t) if t == token => Ok(()),
+            t => Err(format!("expected {:?} but found {:?}", token, t)),
+        }
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        match self.tokens.next() {
+            Some(Token::Str(s)) | Some(Token::Word(s)) => Ok(s),
+            t => Err(format!("expected a value but found {:?}", t)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.tokens.peek() == Some(&Token::LParen) {
+            self.tokens.next();
+            let expr = self.or()?;
+            self.expect(Token::RParen)?;
+            return Ok(expr);
+        }
+        let field = match self.tokens.next() {
+            Some(Token::Word(field)) => field,
+            t => return Err(format!("expected a field but found {:?}", t)),
+        };
+        self.condition(field)
+    }
+
+    fn condition(&mut self, field: String) -> Result<Expr, String> {
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            if !self.keyword("null") {
+                return Err(format!("expected null after is for {}", field));
+            }
+            let expr = Expr::IsNull(field);
+            return Ok(if negated {
+                Expr::Not(Box::new(expr))
+            } else {
+                expr
+            });
+        }
+        let negated = self.keyword("not");
+        let (op, negated) = match self.tokens.next() {
+            Some(Token::Op(op)) => match op.as_str() {
+                "=" | "==" => (Op::Eq(self.value()?), negated),
+                "!=" => (Op::Eq(self.value()?), !negated),
+                "<" => (Op::Cmp(Ordering::Less, false, self.value()?), negated),
+                "<=" => (Op::Cmp(Ordering::Less, true, self.value()?), negated),
+                ">" => (Op::Cmp(Ordering::Greater, false, self.value()?), negated),
+                ">=" => (Op::Cmp(Ordering::Greater, true, self.value()?), negated),
+                "=~" => (Op::Matches(regex(&self.value()?)?), negated),
+                _ => return Err(format!("unknown operator {}", op)),
+            },
+            Some(Token::Word(op)) => match op.to_lowercase().as_str() {
+                "contains" => (Op::Contains(self.value()?), negated),
+                "startswith" => (Op::StartsWith(self.value()?), negated),
+                "endswith" => (Op::EndsWith(self.value()?), negated),
+                "matches" => (Op::Matches(regex(&self.value()?)?), negated),
+                "in" => (Op::In(self.in_items()?), negated),
+                _ => return Err(format!("unknown operator {}", op)),
+            },
+            t => {
+                return Err(format!(
+                    "expected an operator after {} but found {:?}",
+                    field, t
+                ));
+            }
+        };
+        let expr = Expr::Cond(field, op);
+        Ok(if negated {
+            Expr::Not(Box::new(expr))
+        } else {
+            expr
+        })
+    }
+
+    fn in_items(&mut self) -> Result<Vec<InItem>, String> {
+        let close = match self.tokens.peek() {
+            Some(Token::LBracket) => Token::RBracket,
+            Some(Token::LParen) => Token::RParen,
+            _ => return Ok(vec![in_item(self.value()?)]),
+        };
+        self.tokens.next();
+        let mut items = vec![];
+        loop {
+            items.push(in_item(self.value()?));
+            match self.tokens.next() {
+                Some(Token::Comma) => continue,
+                Some(t) if t == close => return Ok(items),
+                t => return Err(format!("expected , or {:?} but found {:?}", close, t)),
+            }
+        }
+    }
+}
+
+fn regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| e.to_string())
+}
+
+fn in_item(value: String) -> InItem {
+    match IpCidr::from_str(&value) {
+        Ok(cidr) if value.contains('/') => InItem::Cidr(cidr),
+        _ => InItem::Value(value),
+    }
+}
+
+/// Parses a `--where` expression for clap.
+pub fn parse_where(s: &str) -> Result<WhereExpr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(s)?.into_iter().peekable(),
+    };
+    let expr = parser.or()?;
+    match parser.tokens.next() {
+        None => Ok(WhereExpr(expr)),
+        Some(t) => Err(format!("unexpected {:?}", t)),
+    }
+}
+
+// The values of a field. When the path reaches an array, the rest of the path is looked up in
+// each element (ex: `resources.ARN` returns the ARN of every resource).
+fn json_values<'a>(json: &'a Value, field: &str) -> Vec<&'a Value> {
+    let mut values = vec![json];
+    for key in field.split('.') {
+        values = values
+            .into_iter()
+            .flat_map(|v| match v {
+                Value::Array(elements) => elements.iter().filter_map(|e| e.get(key)).collect(),
+                _ => v.get(key).into_iter().collect::<Vec<_>>(),
+            })
+            .collect();
+    }
+    values
+}
+
+// The scalar values of a field. The elements are returned for an array field.
+fn field_values(json: &Value, field: &str) -> Vec<String> {
+    let to_string = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(v.to_string()),
+        _ => None,
+    };
+    json_values(json, field)
+        .into_iter()
+        .flat_map(|v| match v {
+            Value::Array(values) => values.iter().filter_map(to_string).collect(),
+            v => to_string(v).into_iter().collect::<Vec<_>>(),
+        })
+        .collect()
+}
+
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+impl Op {
+    fn is_match(&self, v: &str) -> bool {
+        match self {
+            Op::Eq(x) => v == x,
+            Op::Cmp(ord, or_equal, x) => {
+                let res = compare(v, x);
+                res == *ord || (*or_equal && res == Ordering::Equal)
+            }
+            Op::Contains(x) => v.contains(x.as_str()),
+            Op::StartsWith(x) => v.starts_with(x.as_str()),
+            Op::EndsWith(x) => v.ends_with(x.as_str()),
+            Op::Matches(re) => re.is_match(v),
+            Op::In(items) => items.iter().any(|item| match item {
+                InItem::Value(x) => v == x,
+                InItem::Cidr(cidr) => IpAddr::from_str(v.trim_start_matches("::ffff:"))
+                    .is_ok_and(|ip| cidr.contains(&ip)),
+            }),
+        }
+    }
+}
+
+impl Expr {
+    fn is_match(&self, json: &Value) -> bool {
+        match self {
+            Expr::And(a, b) => a.is_match(json) && b.is_match(json),
+            Expr::Or(a, b) => a.is_match(json) || b.is_match(json),
+            Expr::Not(e) => !e.is_match(json),
+            Expr::IsNull(field) => json_values(json, field).iter().all(|v| v.is_null()),
+            Expr::Cond(field, op) => field_values(json, field).iter().any(|v| op.is_match(v)),
+        }
+    }
+}
+
+impl WhereExpr {
+    pub fn is_match(&self, json: &Value) -> bool {
+        self.0.is_match(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_where_expr() {
+        let event = json!({
+            "eventName": "CreateUser",
+            "eventVersion": "1.08",
+            "sourceIPAddress": "192.0.2.1",
+            "readOnly": false,
+            "userIdentity": {"type": "IAMUser", "arn": "arn:aws:iam::123456789012:user/alice"},
+            "errorCode": null,
+            "resources": [{"ARN": "arn:aws:iam::123456789012:user/bob"}],
+            "tags": ["a", "b"]
+        });
+        let is_match = |s: &str| parse_where(s).unwrap().is_match(&event);
+        assert!(is_match(
+            r#"eventName startswith "Create" and errorCode is null and sourceIPAddress not in 10.0.0.0/8"#
+        ));
+        assert!(is_match(
+            "sourceIPAddress in [198.51.100.0/24, 192.0.2.0/24]"
+        ));
+        assert!(!is_match("sourceIPAddress in 10.0.0.0/8"));
+        assert!(is_match(
+            "userIdentity.type == IAMUser AND readOnly = false"
+        ));
+        assert!(is_match("userIdentity.arn matches ':user/(alice|bob)$'"));
+        assert!(is_match(
+            "userIdentity.arn =~ alice and not eventName endswith Group"
+        ));
+        assert!(is_match("eventVersion >= 1.05 and eventVersion < 1.10"));
+        assert!(is_match("tags contains b"));
+        assert!(is_match("errorMessage is null and eventName is not null"));
+        assert!(is_match(
+            "(eventName == DeleteUser or eventName == CreateUser) and eventName != X"
+        ));
+        assert!(!is_match("not (eventName contains Create)"));
+        assert!(is_match(
+            "eventName == DeleteUser or resources.ARN is not null"
+        ));
+        assert!(is_match("resources.ARN endswith user/bob"));
+        assert!(!is_match("resources.ARN endswith user/alice"));
+        assert!(is_match("resources.accountId is null"));
+
+        assert!(parse_where("eventName ==").is_err());
+        assert!(parse_where("eventName like Create").is_err());
+        assert!(parse_where("(eventName == A").is_err());
+        assert!(parse_where("eventName matches '('").is_err());
+        assert!(parse_where("eventName == A B").is_err());
+    }
+}
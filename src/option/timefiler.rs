@@ -1,5 +1,5 @@
 use crate::option::cli::TimeOption;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde_json::Value;
 
 pub fn filter_by_time(opt: &TimeOption, value: &Value) -> bool {
@@ -11,95 +11,267 @@ pub fn filter_by_time(opt: &TimeOption, value: &Value) -> bool {
         Ok(dt) => dt.with_timezone(&Utc),
         Err(_) => return false,
     };
-    if let Some(start) = &opt.timeline_start {
-        let start_time = match DateTime::parse_from_rfc3339(start) {
-            Ok(dt) => dt.with_timezone(&Utc),
-            Err(_) => return false,
-        };
-        if event_time < start_time {
-            return false;
+    if opt.timeline_start.is_some_and(|start| event_time < start) {
+        return false;
+    }
+    if opt.timeline_end.is_some_and(|end| event_time > end) {
+        return false;
+    }
+    if opt.offset_start.is_some_and(|start| event_time < start) {
+        return false;
+    }
+    true
+}
+
+impl TimeOption {
+    /// Resolves --time-offset to the start time. It is called once at startup, so the same
+    /// events are loaded during a long scan or --follow.
+    pub fn resolve_offset(&mut self, now: DateTime<Utc>) {
+        self.offset_start = self.time_offset.map(|offset| offset.start_time(now));
+    }
+}
+
+/// An offset of --time-offset. Years and months are subtracted as calendar months.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeOffset {
+    months: u32,
+    duration: Duration,
+}
+
+impl TimeOffset {
+    pub fn start_time(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now.checked_sub_months(Months::new(self.months))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+            - self.duration
+    }
+}
+
+// Parses compound offsets like 1y6M, 1d12h or 90s.
+fn parse_offset_units(offset: &str) -> Option<TimeOffset> {
+    let mut result = TimeOffset::default();
+    let mut num = String::new();
+    let mut has_unit = false;
+    for c in offset.trim().chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
         }
+        let n: u32 = num.parse().ok()?;
+        num.clear();
+        has_unit = true;
+        let duration = match c {
+            'y' | 'M' => {
+                let months = if c == 'y' { n.checked_mul(12)? } else { n };
+                result.months = result.months.checked_add(months)?;
+                continue;
+            }
+            'w' => Duration::try_weeks(n.into())?,
+            'd' => Duration::try_days(n.into())?,
+            'h' => Duration::try_hours(n.into())?,
+            'm' => Duration::try_minutes(n.into())?,
+            's' => Duration::try_seconds(n.into())?,
+            _ => return None,
+        };
+        result.duration = result.duration.checked_add(&duration)?;
     }
-    if let Some(end) = &opt.timeline_end {
-        let end_time = match DateTime::parse_from_rfc3339(end) {
-            Ok(dt) => dt.with_timezone(&Utc),
-            Err(_) => return false,
+    // a number without a unit or an empty string
+    if !num.is_empty() || !has_unit {
+        return None;
+    }
+    Some(result)
+}
+
+/// Parses a duration like 30s, 10m or 1d12h. Years and months are not accepted
+/// as their length varies.
+pub fn parse_offset(offset: &str) -> Option<Duration> {
+    parse_offset_units(offset)
+        .filter(|o| o.months == 0)
+        .map(|o| o.duration)
+}
+
+/// Parses --time-offset for clap.
+pub fn parse_time_offset(offset: &str) -> Result<TimeOffset, String> {
+    parse_offset_units(offset).ok_or(
+        "the format must be a number and a unit of y, M, w, d, h, m or s (ex: 3M, 1d12h)"
+            .to_string(),
+    )
+}
+
+fn parse_time_with(time: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    let time = time.trim();
+    // epoch seconds or milliseconds
+    if let Ok(epoch) = time.parse::<i64>() {
+        let dt = if epoch.abs() >= 100_000_000_000 {
+            DateTime::from_timestamp_millis(epoch)
+        } else {
+            DateTime::from_timestamp(epoch, 0)
         };
-        if event_time > end_time {
-            return false;
+        return dt.ok_or("the epoch time is out of range".to_string());
+    }
+    // accept a space between the date and the time, and before the timezone
+    let mut normalized = time.to_string();
+    if normalized.len() > 10 && normalized.as_bytes()[10] == b' ' {
+        normalized.replace_range(10..11, "T");
+    }
+    let normalized = normalized.replace(' ', "");
+    if let Ok(dt) = DateTime::parse_from_rfc3339(&normalized) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S%.f%z",
+        "%Y-%m-%dT%H:%M%:z",
+        "%Y-%m-%dT%H:%M%z",
+    ] {
+        if let Ok(dt) = DateTime::parse_from_str(&normalized, fmt) {
+            return Ok(dt.with_timezone(&Utc));
         }
     }
-    if let Some(offset) = &opt.time_offset {
-        let now = Utc::now();
-        let duration = match parse_offset(offset) {
-            Some(d) => d,
-            None => return false,
-        };
-        if event_time < now - duration {
-            return false;
+    // times without a timezone are UTC
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(&normalized, fmt) {
+            return Ok(dt.and_utc());
         }
     }
-    true
-}
-fn parse_offset(offset: &str) -> Option<Duration> {
-    let (num, unit) = offset.trim().split_at(offset.len() - 1);
-    let n: i64 = num.parse().ok()?;
-    match unit {
-        "y" => Some(Duration::days(n * 365)),
-        "M" => Some(Duration::days(n * 30)),
-        "d" => Some(Duration::days(n)),
-        "h" => Some(Duration::hours(n)),
-        "m" => Some(Duration::minutes(n)),
-        _ => None,
+    for fmt in ["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(&normalized, fmt) {
+            let time = if end_of_day {
+                NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap()
+            } else {
+                NaiveTime::MIN
+            };
+            return Ok(date.and_time(time).and_utc());
+        }
     }
+    Err("the format must be a date (ex: 2024-08-18), a date and time (ex: 2024-08-18 13:00:00, 2024-08-18T13:00:00+09:00) or an epoch time".to_string())
+}
+
+/// Parses --timeline-start for clap. A date without a time is the start of the day in UTC.
+pub fn parse_start_time(time: &str) -> Result<DateTime<Utc>, String> {
+    parse_time_with(time, false)
+}
+
+/// Parses --timeline-end for clap. A date without a time is the end of the day in UTC.
+pub fn parse_end_time(time: &str) -> Result<DateTime<Utc>, String> {
+    parse_time_with(time, true)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use serde_json::json;
 
+    fn time_opt(start: Option<&str>, end: Option<&str>, offset: Option<&str>) -> TimeOption {
+        let mut opt = TimeOption {
+            timeline_start: start.map(|s| parse_start_time(s).unwrap()),
+            timeline_end: end.map(|s| parse_end_time(s).unwrap()),
+            time_offset: offset.map(|s| parse_time_offset(s).unwrap()),
+            offset_start: None,
+        };
+        opt.resolve_offset(Utc::now());
+        opt
+    }
+
     #[test]
     fn test_filter_by_time_within_range() {
-        let opt = TimeOption {
-            timeline_start: Some("2024-08-18T12:00:00Z".to_string()),
-            timeline_end: Some("2024-08-18T14:00:00Z".to_string()),
-            time_offset: None,
-        };
+        let opt = time_opt(
+            Some("2024-08-18T12:00:00Z"),
+            Some("2024-08-18T14:00:00Z"),
+            None,
+        );
         let value = json!({ "eventTime": "2024-08-18T13:00:00Z" });
         assert!(filter_by_time(&opt, &value));
     }
 
     #[test]
     fn test_filter_by_time_outside_range() {
-        let opt = TimeOption {
-            timeline_start: Some("2024-08-18T12:00:00Z".to_string()),
-            timeline_end: Some("2024-08-18T14:00:00Z".to_string()),
-            time_offset: None,
-        };
+        let opt = time_opt(
+            Some("2024-08-18T12:00:00Z"),
+            Some("2024-08-18T14:00:00Z"),
+            None,
+        );
         let value = json!({ "eventTime": "2024-08-18T15:00:00Z" });
         assert!(!filter_by_time(&opt, &value));
     }
 
     #[test]
     fn test_filter_by_time_with_offset() {
-        let opt = TimeOption {
-            timeline_start: None,
-            timeline_end: None,
-            time_offset: Some("1h".to_string()),
-        };
+        let opt = time_opt(None, None, Some("1h"));
         let value = json!({ "eventTime": (Utc::now() - Duration::minutes(30)).to_rfc3339() });
         assert!(filter_by_time(&opt, &value));
     }
 
+    #[test]
+    fn test_filter_by_time_with_resolved_offset() {
+        let mut opt = time_opt(None, None, Some("1h"));
+        opt.resolve_offset(Utc.with_ymd_and_hms(2024, 8, 18, 14, 0, 0).unwrap());
+        let value = json!({ "eventTime": "2024-08-18T13:30:00Z" });
+        assert!(filter_by_time(&opt, &value));
+        let value = json!({ "eventTime": "2024-08-18T12:30:00Z" });
+        assert!(!filter_by_time(&opt, &value));
+    }
+
     #[test]
     fn test_filter_by_time_with_invalid_event_time() {
-        let opt = TimeOption {
-            timeline_start: Some("2024-08-18T12:00:00Z".to_string()),
-            timeline_end: Some("2024-08-18T14:00:00Z".to_string()),
-            time_offset: None,
-        };
+        let opt = time_opt(
+            Some("2024-08-18T12:00:00Z"),
+            Some("2024-08-18T14:00:00Z"),
+            None,
+        );
         let value = json!({ "eventTime": "invalid-date" });
         assert!(!filter_by_time(&opt, &value));
     }
+
+    #[test]
+    fn test_parse_time() {
+        let expected = Utc.with_ymd_and_hms(2024, 8, 18, 13, 0, 0).unwrap();
+        for time in [
+            "2024-08-18T13:00:00Z",
+            "2024-08-18 13:00:00",
+            "2024-08-18T13:00",
+            "2024-08-18 22:00:00 +09:00",
+            "2024-08-18T22:00:00+0900",
+            "2024-08-18T13:00:00.000Z",
+            "1723986000",
+            "1723986000000",
+        ] {
+            assert_eq!(parse_start_time(time), Ok(expected), "{}", time);
+        }
+        let date = Utc.with_ymd_and_hms(2024, 8, 18, 0, 0, 0).unwrap();
+        assert_eq!(parse_start_time("2024-08-18"), Ok(date));
+        assert_eq!(
+            parse_end_time("2024/08/18"),
+            Ok(date + Duration::days(1) - Duration::nanoseconds(1))
+        );
+        assert!(parse_start_time("").is_err());
+        assert!(parse_start_time("2024-13-01").is_err());
+        assert!(parse_start_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("30s"), Some(Duration::seconds(30)));
+        assert_eq!(parse_offset("1d12h"), Some(Duration::hours(36)));
+        assert_eq!(parse_offset("1w2m"), Some(Duration::minutes(10082)));
+        assert_eq!(parse_offset(""), None);
+        assert_eq!(parse_offset("10"), None);
+        assert_eq!(parse_offset("h"), None);
+        assert_eq!(parse_offset("1x"), None);
+        assert_eq!(parse_offset("1M"), None);
+
+        // months are calendar months
+        let now = Utc.with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap();
+        let offset = parse_time_offset("1M1d").unwrap();
+        assert_eq!(
+            offset.start_time(now),
+            Utc.with_ymd_and_hms(2024, 2, 28, 12, 0, 0).unwrap()
+        );
+        let offset = parse_time_offset("1y").unwrap();
+        assert_eq!(
+            offset.start_time(now),
+            Utc.with_ymd_and_hms(2023, 3, 31, 12, 0, 0).unwrap()
+        );
+        assert!(parse_time_offset("").is_err());
+        assert!(parse_time_offset("99999999999d").is_err());
+    }
 }
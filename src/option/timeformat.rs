@@ -0,0 +1,149 @@
+use crate::option::cli::TimeFormatOptions;
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
+use chrono_tz::Tz;
+
+enum Zone {
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+enum Format {
+    Default,
+    Iso8601,
+    Rfc2822,
+    Rfc3339,
+    Us,
+    European,
+}
+
+pub struct TimeFormatter {
+    zone: Zone,
+    format: Format,
+}
+
+impl TimeFormatter {
+    pub fn new(opt: &TimeFormatOptions) -> Result<Self, String> {
+        let zone = match opt.timezone.as_deref() {
+            _ if opt.utc => Zone::Utc,
+            None => Zone::Utc,
+            Some(tz) if tz.eq_ignore_ascii_case("utc") => Zone::Utc,
+            Some(tz) if tz.eq_ignore_ascii_case("local") => Zone::Local,
+            Some(tz) => match tz.parse::<Tz>() {
+                Ok(tz) => Zone::Named(tz),
+                Err(_) => {
+                    return Err(format!(
+                        "Invalid timezone: {}. Please specify an IANA timezone name (ex: Asia/Tokyo, America/New_York) or local.",
+                        tz
+                    ));
+                }
+            },
+        };
+        let format = if opt.iso_8601 {
+            Format::Iso8601
+        } else if opt.rfc_2822 {
+            Format::Rfc2822
+        } else if opt.rfc_3339 {
+            Format::Rfc3339
+        } else if opt.us_time {
+            Format::Us
+        } else if opt.european_time {
+            Format::European
+        } else {
+            Format::Default
+        };
+        Ok(TimeFormatter { zone, format })
+    }
+
+    pub fn format(&self, time: &DateTime<Utc>) -> String {
+        let time: DateTime<FixedOffset> = match &self.zone {
+            Zone::Utc => time.fixed_offset(),
+            Zone::Local => time.with_timezone(&Local).fixed_offset(),
+            Zone::Named(tz) => time.with_timezone(tz).fixed_offset(),
+        };
+        match self.format {
+            Format::Default if matches!(self.zone, Zone::Utc) => {
+                time.format("%Y-%m-%d %H:%M:%S%.f").to_string()
+            }
+            Format::Default => time.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string(),
+            Format::Iso8601 => time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Format::Rfc2822 => time.to_rfc2822(),
+            Format::Rfc3339 => time.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string(),
+            Format::Us => time.format("%m-%d-%Y %I:%M:%S%.f %p %:z").to_string(),
+            Format::European => time.format("%d-%m-%Y %H:%M:%S%.f %:z").to_string(),
+        }
+    }
+
+    /// Formats a CloudTrail timestamp string (ex: 2024-08-18T13:00:00Z).
+    /// Values that cannot be parsed are returned as they are.
+    pub fn format_str(&self, time: &str) -> String {
+        match DateTime::parse_from_rfc3339(time) {
+            Ok(dt) => self.format(&dt.with_timezone(&Utc)),
+            Err(_) => time.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter(timezone: Option<&str>, set: fn(&mut TimeFormatOptions)) -> TimeFormatter {
+        let mut opt = TimeFormatOptions {
+            timezone: timezone.map(|s| s.to_string()),
+            ..Default::default()
+        };
+        set(&mut opt);
+        TimeFormatter::new(&opt).unwrap()
+    }
+
+    #[test]
+    fn test_format_default_utc() {
+        let fmt = formatter(None, |_| {});
+        assert_eq!(
+            fmt.format_str("2024-08-18T13:00:00Z"),
+            "2024-08-18 13:00:00"
+        );
+    }
+
+    #[test]
+    fn test_format_timezone() {
+        let fmt = formatter(Some("Asia/Tokyo"), |_| {});
+        assert_eq!(
+            fmt.format_str("2024-08-18T23:00:00Z"),
+            "2024-08-19 08:00:00 +09:00"
+        );
+        let fmt = formatter(Some("Asia/Tokyo"), |o| o.iso_8601 = true);
+        assert_eq!(
+            fmt.format_str("2024-08-18T23:00:00Z"),
+            "2024-08-19T08:00:00+09:00"
+        );
+        let fmt = formatter(Some("America/New_York"), |o| o.us_time = true);
+        assert_eq!(
+            fmt.format_str("2024-08-18T23:00:00Z"),
+            "08-18-2024 07:00:00 PM -04:00"
+        );
+    }
+
+    #[test]
+    fn test_format_styles_utc() {
+        let time = "2024-08-18T13:00:00Z";
+        let fmt = formatter(None, |o| o.iso_8601 = true);
+        assert_eq!(fmt.format_str(time), "2024-08-18T13:00:00Z");
+        let fmt = formatter(None, |o| o.rfc_2822 = true);
+        assert_eq!(fmt.format_str(time), "Sun, 18 Aug 2024 13:00:00 +0000");
+        let fmt = formatter(None, |o| o.rfc_3339 = true);
+        assert_eq!(fmt.format_str(time), "2024-08-18 13:00:00+00:00");
+        let fmt = formatter(None, |o| o.european_time = true);
+        assert_eq!(fmt.format_str(time), "18-08-2024 13:00:00 +00:00");
+    }
+
+    #[test]
+    fn test_invalid_timezone() {
+        let opt = TimeFormatOptions {
+            timezone: Some("Mars/Olympus".to_string()),
+            ..Default::default()
+        };
+        assert!(TimeFormatter::new(&opt).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+pub mod elastic;
+#[cfg(test)]
+mod fixture;
+pub mod html;
+pub mod http;
+pub mod insight;
+pub mod ocsf;
+pub mod parquet;
+pub mod pivot;
+pub mod sqlite;
+pub mod stix;
+pub mod summary;
+pub mod syslog;
+pub mod timesketch;
@@ -0,0 +1,341 @@
+use crate::core::aggregate::{AGGREGATION_COUNT, AGGREGATION_FIRST_TIME, AGGREGATION_LAST_TIME};
+use crate::core::util::{get_json_str, get_json_value};
+use serde_json::{Map, Value, json};
+use sigma_rust::Rule;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+pub const INDEX_NAME: &str = "suzaku-aws-ct";
+const ECS_VERSION: &str = "8.11.0";
+
+/// Writes detections in the Elasticsearch/OpenSearch `_bulk` NDJSON format with ECS field names.
+/// Events without detections (--all-events) are written with `event.kind: event`.
+pub struct ElasticWriter {
+    writer: BufWriter<Box<dyn Write>>,
+}
+
+impl ElasticWriter {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Ok(ElasticWriter {
+            writer: BufWriter::new(Box::new(fs::File::create(path)?)),
+        })
+    }
+
+    pub fn write(
+        &mut self,
+        profile: &[(String, String)],
+        record: &[String],
+        json: &Value,
+        rule: Option<&Rule>,
+        raw_output: bool,
+    ) -> io::Result<()> {
+        let action = json!({ "index": { "_index": INDEX_NAME } });
+        let doc = ecs_document(profile, record, json, rule, raw_output);
+        writeln!(self.writer, "{}", action)?;
+        writeln!(self.writer, "{}", doc)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub fn severity(rule: &Rule) -> u8 {
+    let level = rule
+        .level
+        .as_ref()
+        .map(|l| format!("{:?}", l).to_lowercase())
+        .unwrap_or_default();
+    match level.as_str() {
+        "critical" => 99,
+        "high" => 73,
+        "medium" => 47,
+        "low" => 21,
+        _ => 1,
+    }
+}
+
+pub fn ecs_document(
+    profile: &[(String, String)],
+    record: &[String],
+    json: &Value,
+    rule: Option<&Rule>,
+    raw_output: bool,
+) -> Value {
+    let get = |key: &str| get_json_str(json, key).map(|s| Value::String(s.to_string()));
+    let from_profile = |key: &str| {
+        profile
+            .iter()
+            .position(|(k, _)| k == key)
+            .and_then(|i| record.get(i))
+            .filter(|v| v.as_str() != "-")
+            .map(|v| Value::String(v.clone()))
+    };
+    let level = rule
+        .and_then(|r| r.level.as_ref())
+        .map(|l| format!("{:?}", l).to_lowercase());
+    let outcome = if get_json_value(json, "errorCode").is_some() {
+        "failure"
+    } else {
+        "success"
+    };
+    let user_name = get("userIdentity.userName")
+        .or_else(|| get("userIdentity.sessionContext.sessionIssuer.userName"))
+        .or_else(|| {
+            get_json_str(json, "userIdentity.arn")
+                .and_then(|arn| arn.rsplit(['/', ':']).next())
+                .map(|s| Value::String(s.to_string()))
+        });
+    let source_address = get_json_str(json, "sourceIPAddress");
+    // sourceIPAddress can also be an AWS service name (ex: cloudtrail.amazonaws.com)
+    let source_ip = source_address
+        .map(|ip| ip.trim_start_matches("::ffff:"))
+        .filter(|ip| ip.parse::<IpAddr>().is_ok())
+        .map(|ip| Value::String(ip.to_string()));
+
+    let mut doc = Map::new();
+    insert(&mut doc, "@timestamp", get("eventTime"));
+    let (message, kind) = match rule {
+        Some(rule) => (Some(Value::String(rule.title.clone())), "alert"),
+        None => (get("eventName"), "event"),
+    };
+    insert(&mut doc, "message", message);
+    insert(&mut doc, "ecs.version", Some(json!(ECS_VERSION)));
+    insert(&mut doc, "event.kind", Some(json!(kind)));
+    insert(&mut doc, "event.module", Some(json!("aws")));
+    insert(&mut doc, "event.dataset", Some(json!("aws.cloudtrail")));
+    insert(&mut doc, "event.action", get("eventName"));
+    insert(&mut doc, "event.provider", get("eventSource"));
+    insert(&mut doc, "event.id", get("eventID"));
+    insert(&mut doc, "event.outcome", Some(json!(outcome)));
+    insert(&mut doc, "event.severity", rule.map(|r| json!(severity(r))));
+    insert(&mut doc, "log.level", level.map(Value::String));
+    insert(&mut doc, "source.address", source_address.map(|s| json!(s)));
+    insert(&mut doc, "source.ip", source_ip);
+    insert(
+        &mut doc,
+        "source.as.organization.name",
+        from_profile("SrcASN"),
+    );
+    insert(&mut doc, "source.geo.city_name", from_profile("SrcCity"));
+    insert(
+        &mut doc,
+        "source.geo.country_name",
+        from_profile("SrcCountry"),
+    );
+    insert(&mut doc, "user.name", user_name);
+    insert(&mut doc, "user.id", get("userIdentity.principalId"));
+    insert(&mut doc, "user_agent.original", get("userAgent"));
+    insert(&mut doc, "cloud.provider", Some(json!("aws")));
+    insert(&mut doc, "cloud.region", get("awsRegion"));
+    insert(
+        &mut doc,
+        "cloud.account.id",
+        get("recipientAccountId").or_else(|| get("userIdentity.accountId")),
+    );
+    if let Some(rule) = rule {
+        insert(
+            &mut doc,
+            "rule.name",
+            Some(Value::String(rule.title.clone())),
+        );
+        insert(&mut doc, "rule.id", rule.id.clone().map(Value::String));
+        insert(
+            &mut doc,
+            "rule.author",
+            rule.author.clone().map(Value::String),
+        );
+        insert(
+            &mut doc,
+            "rule.description",
+            rule.description.clone().map(Value::String),
+        );
+        insert(
+            &mut doc,
+            "rule.reference",
+            rule.references.as_ref().map(|r| json!(r)),
+        );
+        insert(&mut doc, "tags", rule.tags.as_ref().map(|t| json!(t)));
+    }
+    insert(
+        &mut doc,
+        "aws.cloudtrail.user_identity.arn",
+        get("userIdentity.arn"),
+    );
+    insert(
+        &mut doc,
+        "aws.cloudtrail.user_identity.type",
+        get("userIdentity.type"),
+    );
+    insert(
+        &mut doc,
+        "aws.cloudtrail.user_identity.access_key_id",
+        get("userIdentity.accessKeyId"),
+    );
+    insert(&mut doc, "aws.cloudtrail.error_code", get("errorCode"));
+    insert(
+        &mut doc,
+        "aws.cloudtrail.error_message",
+        get("errorMessage"),
+    );
+    // aggregated detections (--aggregate-window)
+    insert(&mut doc, "event.start", get(AGGREGATION_FIRST_TIME));
+    insert(&mut doc, "event.end", get(AGGREGATION_LAST_TIME));
+    insert(
+        &mut doc,
+        "suzaku.aggregation.count",
+        get_json_value(json, AGGREGATION_COUNT).cloned(),
+    );
+    if raw_output {
+        insert(&mut doc, "event.original", Some(json!(json.to_string())));
+    }
+    Value::Object(doc)
+}
+
+// Inserts a value with a dotted ECS field name as nested objects.
+fn insert(doc: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    let Some(value) = value else {
+        return;
+    };
+    match key.split_once('.') {
+        Some((head, tail)) if head != "@timestamp" => {
+            let child = doc
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(child) = child {
+                insert(child, tail, Some(value));
+            }
+        }
+        _ => {
+            doc.insert(key.to_string(), value);
+        }
+    }
+}
+
+pub fn index_template_path(output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output.with_file_name(format!("{}-index-template.json", stem))
+}
+
+/// Writes a composable index template for the documents created by `ElasticWriter`.
+pub fn write_index_template(path: &Path) -> io::Result<()> {
+    let keyword = json!({ "type": "keyword" });
+    let template = json!({
+        "index_patterns": [format!("{}*", INDEX_NAME)],
+        "priority": 200,
+        "_meta": { "description": "Suzaku AWS CloudTrail detections (ECS)" },
+        "template": {
+            "mappings": {
+                "dynamic": true,
+                "properties": {
+                    "@timestamp": { "type": "date" },
+                    "message": { "type": "text" },
+                    "ecs": { "properties": { "version": keyword } },
+                    "event": { "properties": {
+                        "kind": keyword, "module": keyword, "dataset": keyword,
+                        "action": keyword, "provider": keyword, "id": keyword,
+                        "outcome": keyword, "severity": { "type": "long" },
+                        "start": { "type": "date" }, "end": { "type": "date" },
+                        "original": { "type": "keyword", "index": false, "doc_values": false }
+                    }},
+                    "log": { "properties": { "level": keyword } },
+                    "source": { "properties": {
+                        "address": keyword,
+                        "ip": { "type": "ip" },
+                        "as": { "properties": { "organization": { "properties": { "name": keyword } } } },
+                        "geo": { "properties": { "city_name": keyword, "country_name": keyword } }
+                    }},
+                    "user": { "properties": { "name": keyword, "id": keyword } },
+                    "user_agent": { "properties": { "original": keyword } },
+                    "cloud": { "properties": {
+                        "provider": keyword, "region": keyword,
+                        "account": { "properties": { "id": keyword } }
+                    }},
+                    "rule": { "properties": {
+                        "name": keyword, "id": keyword, "author": keyword,
+                        "description": { "type": "text" }, "reference": keyword
+                    }},
+                    "tags": keyword,
+                    "suzaku": { "properties": {
+                        "aggregation": { "properties": { "count": { "type": "long" } } }
+                    }},
+                    "aws": { "properties": { "cloudtrail": { "properties": {
+                        "user_identity": { "properties": {
+                            "arn": keyword, "type": keyword, "access_key_id": keyword
+                        }},
+                        "error_code": keyword,
+                        "error_message": { "type": "text" }
+                    }}}}
+                }
+            }
+        }
+    });
+    fs::write(path, serde_json::to_string_pretty(&template)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+
+    #[test]
+    fn test_detection_has_ecs_fields() {
+        let doc = ecs_document(&[], &[], &test_event(), Some(&test_rule("critical")), false);
+        assert_eq!(doc["@timestamp"], "2024-08-18T13:05:25Z");
+        assert_eq!(doc["event"]["kind"], "alert");
+        assert_eq!(doc["event"]["action"], "DeleteTrail");
+        assert_eq!(doc["event"]["severity"], 99);
+        assert_eq!(doc["source"]["ip"], "192.0.2.1");
+        assert_eq!(doc["user"]["name"], "alice");
+        assert_eq!(doc["cloud"]["region"], "us-east-1");
+        assert_eq!(doc["rule"]["name"], "CloudTrail Deleted");
+        assert_eq!(doc["rule"]["id"], "test_rule");
+        assert!(doc.get("suzaku").is_none());
+    }
+
+    #[test]
+    fn test_aggregated_detection_has_count_and_times() {
+        let mut event = test_event();
+        event["suzakuAggregation"] = json!({
+            "count": 3,
+            "firstEventTime": "2024-08-18T13:05:25Z",
+            "lastEventTime": "2024-08-18T13:15:25Z",
+        });
+        let doc = ecs_document(&[], &[], &event, Some(&test_rule("high")), false);
+        assert_eq!(doc["event"]["start"], "2024-08-18T13:05:25Z");
+        assert_eq!(doc["event"]["end"], "2024-08-18T13:15:25Z");
+        assert_eq!(doc["suzaku"]["aggregation"]["count"], 3);
+    }
+
+    #[test]
+    fn test_service_source_is_not_an_ip() {
+        let event = json!({ "sourceIPAddress": "guardduty.amazonaws.com" });
+        let doc = ecs_document(&[], &[], &event, Some(&test_rule("high")), false);
+        assert_eq!(doc["source"]["address"], "guardduty.amazonaws.com");
+        assert!(doc["source"].get("ip").is_none());
+    }
+
+    #[test]
+    fn test_raw_output_adds_original_event() {
+        let rule = test_rule("high");
+        let doc = ecs_document(&[], &[], &test_event(), Some(&rule), false);
+        assert!(doc["event"].get("original").is_none());
+        let doc = ecs_document(&[], &[], &test_event(), Some(&rule), true);
+        assert!(doc["event"]["original"].is_string());
+    }
+
+    #[test]
+    fn test_event_without_detection() {
+        let event = json!({ "eventName": "ListBuckets" });
+        let doc = ecs_document(&[], &[], &event, None, false);
+        assert_eq!(doc["event"]["kind"], "event");
+        assert_eq!(doc["message"], "ListBuckets");
+        assert!(doc.get("rule").is_none());
+        assert!(doc["event"].get("severity").is_none());
+    }
+}
@@ -0,0 +1,43 @@
+//! Rules and events shared by the tests of the output modules.
+use serde_json::{Value, json};
+use sigma_rust::{Rule, rule_from_yaml};
+
+pub fn test_rule_yaml(level: &str) -> String {
+    format!(
+        r#"
+        title: CloudTrail Deleted
+        id: test_rule
+        level: {}
+        logsource:
+          product: aws
+        detection:
+          selection:
+            eventName: DeleteTrail
+          condition: selection
+        "#,
+        level
+    )
+}
+
+pub fn test_rule(level: &str) -> Rule {
+    rule_from_yaml(&test_rule_yaml(level)).unwrap()
+}
+
+pub fn test_event() -> Value {
+    json!({
+        "eventTime": "2024-08-18T13:05:25Z",
+        "eventName": "DeleteTrail",
+        "eventSource": "cloudtrail.amazonaws.com",
+        "eventID": "03383b06",
+        "awsRegion": "us-east-1",
+        "sourceIPAddress": "192.0.2.1",
+        "recipientAccountId": "123456789012",
+        "userAgent": "aws-cli/2.17.32",
+        "userIdentity": {
+            "type": "IAMUser",
+            "arn": "arn:aws:iam::123456789012:user/alice",
+            "userName": "alice",
+            "accessKeyId": "AKIAEXAMPLE"
+        }
+    })
+}
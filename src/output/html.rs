@@ -0,0 +1,363 @@
+use crate::cmd::aws_detect::DetectionSummary;
+use crate::option::cli::VERSION;
+use crate::option::timeformat::TimeFormatter;
+use chrono::{DateTime, NaiveDate, Utc};
+use num_format::{Locale, ToFormattedString};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Detections after this number are not added to the table to keep the report usable in a browser.
+const MAX_TABLE_ROWS: usize = 10_000;
+
+const LEVELS: [&str; 5] = ["critical", "high", "medium", "low", "informational"];
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.6em; } h2 { font-size: 1.2em; margin-top: 1.8em; border-bottom: 1px solid #ddd; }
+table { border-collapse: collapse; font-size: 0.85em; }
+th, td { border: 1px solid #ddd; padding: 4px 8px; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; position: sticky; top: 0; }
+.critical { color: #c00000; font-weight: bold; } .high { color: #e07000; }
+.medium { color: #a08000; } .low { color: #2e8b57; } .informational { color: #555; }
+.cards { display: flex; flex-wrap: wrap; gap: 2em; }
+#search { width: 30em; padding: 4px; margin-bottom: 0.8em; }
+.table-wrap { max-height: 40em; overflow: auto; }
+svg text { font-size: 11px; fill: #555; }
+"#;
+
+const SCRIPT: &str = r##"
+document.getElementById("search").addEventListener("input", function () {
+  const query = this.value.toLowerCase();
+  for (const row of document.querySelectorAll("#detections tbody tr")) {
+    row.style.display = row.textContent.toLowerCase().includes(query) ? "" : "none";
+  }
+});
+"##;
+
+/// Collects the detections and renders a self-contained HTML report with the results summary.
+#[derive(Default)]
+pub struct HtmlReport {
+    rows: Vec<Vec<String>>,
+    total_rows: usize,
+}
+
+impl HtmlReport {
+    pub fn add(&mut self, record: &[String]) {
+        self.total_rows += 1;
+        if self.rows.len() < MAX_TABLE_ROWS {
+            self.rows.push(record.to_vec());
+        }
+    }
+
+    pub fn write(
+        &self,
+        path: &Path,
+        profile: &[(String, String)],
+        sum: &DetectionSummary,
+        time_fmt: &TimeFormatter,
+    ) -> io::Result<()> {
+        fs::write(path, self.render(profile, sum, time_fmt))
+    }
+
+    fn render(
+        &self,
+        profile: &[(String, String)],
+        sum: &DetectionSummary,
+        time_fmt: &TimeFormatter,
+    ) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>Suzaku Report</title>\n");
+        let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+        let _ = writeln!(html, "<h1>Suzaku AWS CloudTrail Detection Report</h1>");
+        let _ = writeln!(
+            html,
+            "<p>Generated by Suzaku {} at {}</p>",
+            escape(VERSION),
+            escape(&time_fmt.format(&Utc::now()))
+        );
+        render_summary(&mut html, sum, time_fmt);
+        render_frequency_chart(&mut html, &sum.timestamps);
+        render_top_alerts(&mut html, sum);
+        render_authors(&mut html, sum);
+        self.render_detections(&mut html, profile);
+        let _ = writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT);
+        html
+    }
+
+    fn render_detections(&self, html: &mut String, profile: &[(String, String)]) {
+        html.push_str("<h2>Detections</h2>\n");
+        if self.total_rows > self.rows.len() {
+            let _ = writeln!(
+                html,
+                "<p>Showing the first {} of {} detections.</p>",
+                self.rows.len().to_formatted_string(&Locale::en),
+                self.total_rows.to_formatted_string(&Locale::en)
+            );
+        }
+        html.push_str("<input id=\"search\" type=\"search\" placeholder=\"Search detections\">\n");
+        html.push_str("<div class=\"table-wrap\"><table id=\"detections\">\n<thead><tr>");
+        for (k, _) in profile {
+            let _ = write!(html, "<th>{}</th>", escape(k));
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        let level_index = profile.iter().position(|(k, _)| k == "Level");
+        for row in &self.rows {
+            let class = level_index
+                .and_then(|i| row.get(i))
+                .map(|l| l.to_lowercase())
+                .unwrap_or_default();
+            let _ = write!(html, "<tr class=\"{}\">", escape(&class));
+            for col in row {
+                let _ = write!(html, "<td>{}</td>", escape(col));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</tbody>\n</table></div>\n");
+    }
+}
+
+fn render_summary(html: &mut String, sum: &DetectionSummary, time_fmt: &TimeFormatter) {
+    html.push_str("<h2>Results Summary</h2>\n");
+    let reduction = sum.total_events - sum.event_with_hits;
+    let reduction_pct = if sum.total_events == 0 {
+        0.0
+    } else {
+        reduction as f64 * 100.0 / sum.total_events as f64
+    };
+    let _ = writeln!(
+        html,
+        "<p>Events with hits / Total events: {} / {} (Data reduction: {} events ({:.2}%))</p>",
+        sum.event_with_hits.to_formatted_string(&Locale::en),
+        sum.total_events.to_formatted_string(&Locale::en),
+        reduction.to_formatted_string(&Locale::en),
+        reduction_pct
+    );
+    if let Some(first) = sum.first_event_time {
+        let _ = writeln!(
+            html,
+            "<p>First event time: {}</p>",
+            escape(&time_fmt.format(&first))
+        );
+    }
+    if let Some(last) = sum.last_event_time {
+        let _ = writeln!(
+            html,
+            "<p>Last event time: {}</p>",
+            escape(&time_fmt.format(&last))
+        );
+    }
+    html.push_str("<div class=\"cards\">\n<table>\n");
+    html.push_str("<tr><th>Level</th><th>Total detections</th><th>Unique detections</th></tr>\n");
+    for level in LEVELS {
+        let (total, uniq) = sum
+            .level_with_hits
+            .get(level)
+            .map(|hits| (hits.values().sum::<usize>(), hits.len()))
+            .unwrap_or((0, 0));
+        let _ = writeln!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td></tr>",
+            level,
+            level,
+            total.to_formatted_string(&Locale::en),
+            uniq.to_formatted_string(&Locale::en)
+        );
+    }
+    html.push_str("</table>\n<table>\n");
+    html.push_str("<tr><th>Level</th><th>Date with most total detections</th></tr>\n");
+    for level in LEVELS {
+        let date = sum
+            .dates_with_hits
+            .get(level)
+            .and_then(|dates| dates.iter().max_by_key(|&(_, &count)| count))
+            .map(|(date, count)| format!("{} ({})", date, count.to_formatted_string(&Locale::en)))
+            .unwrap_or("n/a".to_string());
+        let _ = writeln!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td></tr>",
+            level,
+            level,
+            escape(&date)
+        );
+    }
+    html.push_str("</table>\n</div>\n");
+}
+
+fn render_top_alerts(html: &mut String, sum: &DetectionSummary) {
+    html.push_str("<h2>Top Alerts</h2>\n<div class=\"cards\">\n");
+    for level in LEVELS {
+        let _ = writeln!(
+            html,
+            "<table><tr><th class=\"{}\">Top {} alerts</th><th>Count</th></tr>",
+            level, level
+        );
+        let mut hits: Vec<(&String, &usize)> = sum
+            .level_with_hits
+            .get(level)
+            .map(|hits| hits.iter().collect())
+            .unwrap_or_default();
+        hits.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        if hits.is_empty() {
+            html.push_str("<tr><td>n/a</td><td></td></tr>\n");
+        }
+        for (rule, count) in hits.into_iter().take(5) {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(rule),
+                count.to_formatted_string(&Locale::en)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</div>\n");
+}
+
+fn render_authors(html: &mut String, sum: &DetectionSummary) {
+    html.push_str("<h2>Rule Authors</h2>\n<table>\n");
+    html.push_str("<tr><th>Author</th><th>Detected rules</th></tr>\n");
+    let mut authors: Vec<(&String, usize)> = sum
+        .author_titles
+        .iter()
+        .map(|(author, titles)| (author, titles.len()))
+        .collect();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    for (author, count) in authors {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            escape(author),
+            count
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+// Renders the number of detections per day as an SVG bar chart.
+fn render_frequency_chart(html: &mut String, timestamps: &[i64]) {
+    html.push_str("<h2>Detection Frequency Timeline</h2>\n");
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for ts in timestamps {
+        if let Some(time) = DateTime::<Utc>::from_timestamp(*ts, 0) {
+            *per_day.entry(time.date_naive()).or_default() += 1;
+        }
+    }
+    let (Some(&first), Some(&last)) = (per_day.keys().next(), per_day.keys().next_back()) else {
+        html.push_str("<p>n/a</p>\n");
+        return;
+    };
+    let days: Vec<(NaiveDate, usize)> = first
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .map(|d| (d, per_day.get(&d).copied().unwrap_or(0)))
+        .collect();
+    let max = days.iter().map(|(_, c)| *c).max().unwrap_or(1).max(1);
+    let (width, height, margin) = (1000.0, 200.0, 40.0);
+    let bar_width = (width - margin * 2.0) / days.len() as f64;
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height + margin
+    );
+    let _ = writeln!(
+        html,
+        "<line x1=\"{m}\" y1=\"{y}\" x2=\"{x2}\" y2=\"{y}\" stroke=\"#999\"/>",
+        m = margin,
+        y = height,
+        x2 = width - margin
+    );
+    for (i, (date, count)) in days.iter().enumerate() {
+        let bar_height = (*count as f64 / max as f64) * (height - margin);
+        let _ = writeln!(
+            html,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"#e07000\"><title>{}: {}</title></rect>",
+            margin + bar_width * i as f64,
+            height - bar_height,
+            bar_width * 0.9,
+            bar_height,
+            date,
+            count
+        );
+    }
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\">{}</text>",
+        margin,
+        height + 15.0,
+        first
+    );
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        width - margin,
+        height + 15.0,
+        last
+    );
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        margin - 4.0,
+        margin,
+        max
+    );
+    html.push_str("</svg>\n");
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::option::cli::TimeFormatOptions;
+
+    fn render(title: &str, level: &str) -> String {
+        let mut sum = DetectionSummary {
+            total_events: 10,
+            event_with_hits: 2,
+            timestamps: vec![1723986325, 1724072725],
+            ..Default::default()
+        };
+        sum.level_with_hits
+            .entry(level.to_string())
+            .or_default()
+            .insert(title.to_string(), 2);
+        let profile = vec![
+            ("RuleTitle".to_string(), "sigma.title".to_string()),
+            ("Level".to_string(), "sigma.level".to_string()),
+        ];
+        let mut report = HtmlReport::default();
+        report.add(&[title.to_string(), level.to_string()]);
+        let time_fmt = TimeFormatter::new(&TimeFormatOptions::default()).unwrap();
+        report.render(&profile, &sum, &time_fmt)
+    }
+
+    #[test]
+    fn test_values_are_escaped() {
+        let html = render("<script>Rule</script>", "high");
+        assert!(html.contains("&lt;script&gt;Rule&lt;/script&gt;"));
+        assert!(!html.contains("<script>Rule"));
+    }
+
+    #[test]
+    fn test_rows_have_level_class() {
+        let html = render("Test Rule", "high");
+        assert!(html.contains("<tr class=\"high\"><td>"));
+    }
+
+    #[test]
+    fn test_daily_histogram() {
+        let html = render("Test Rule", "high");
+        assert!(html.contains("<title>2024-08-19: 1</title>"));
+    }
+}
@@ -0,0 +1,401 @@
+use crate::core::util::get_json_str;
+use crate::option::cli::HttpOutputOptions;
+use chrono::DateTime;
+use serde_json::{Value, json};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use ureq::Agent;
+use ureq::tls::{PemItem, RootCerts, TlsConfig, parse_pem};
+
+// The wait time before the first retry. It is doubled on each retry.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// While the endpoint is down, the detections are spooled without sending for this period.
+const ENDPOINT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HttpFormat {
+    SplunkHec,
+    Webhook,
+}
+
+/// Sends detections to an HTTP endpoint in batches.
+/// Batches that could not be sent are saved to the spool file so the scan can continue.
+pub struct HttpSink {
+    agent: Agent,
+    url: String,
+    format: HttpFormat,
+    auth: Option<String>,
+    batch_size: usize,
+    retries: u32,
+    batch: Vec<Value>,
+    spool_path: PathBuf,
+    spool: Option<File>,
+    // The endpoint is not tried again until this time after it failed.
+    down_until: Option<Instant>,
+    cooldown: Duration,
+    pub sent: usize,
+    pub spooled: usize,
+    // Detections that could not be sent nor saved to the spool file
+    pub dropped: usize,
+}
+
+impl HttpSink {
+    pub fn new(opt: &HttpOutputOptions) -> Result<Self, String> {
+        let url = opt.http_url.clone().unwrap_or_default();
+        let format = if opt.http_format == "hec" {
+            HttpFormat::SplunkHec
+        } else {
+            HttpFormat::Webhook
+        };
+        let mut tls = TlsConfig::builder().disable_verification(opt.http_insecure);
+        if let Some(path) = &opt.http_ca_cert {
+            let pem =
+                fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            let certs: Vec<_> = parse_pem(&pem)
+                .filter_map(|item| match item {
+                    Ok(PemItem::Certificate(cert)) => Some(cert),
+                    _ => None,
+                })
+                .collect();
+            if certs.is_empty() {
+                return Err(format!("No certificates found in {}", path.display()));
+            }
+            tls = tls.root_certs(RootCerts::new_with_certs(&certs));
+        }
+        let agent: Agent = Agent::config_builder()
+            .tls_config(tls.build())
+            .http_status_as_error(false)
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        let auth = opt.http_token.as_ref().map(|token| match format {
+            HttpFormat::SplunkHec => format!("Splunk {}", token),
+            HttpFormat::Webhook => format!("Bearer {}", token),
+        });
+        Ok(HttpSink {
+            agent,
+            url,
+            format,
+            auth,
+            batch_size: opt.http_batch_size.max(1) as usize,
+            retries: opt.http_retries,
+            batch: vec![],
+            spool_path: opt.http_spool.clone(),
+            spool: None,
+            down_until: None,
+            cooldown: ENDPOINT_COOLDOWN,
+            sent: 0,
+            spooled: 0,
+            dropped: 0,
+        })
+    }
+
+    pub fn spool_path(&self) -> &PathBuf {
+        &self.spool_path
+    }
+
+    /// Adds a detection (the profile fields) to the current batch.
+    pub fn write(&mut self, doc: Value, json: &Value) {
+        let item = match self.format {
+            HttpFormat::SplunkHec => {
+                let mut event = json!({
+                    "source": "suzaku",
+                    "sourcetype": "suzaku:aws:cloudtrail",
+                    "event": doc,
+                });
+                if let Some(time) = get_json_str(json, "eventTime")
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                {
+                    event["time"] = json!(time.timestamp_millis() as f64 / 1000.0);
+                }
+                event
+            }
+            HttpFormat::Webhook => doc,
+        };
+        self.batch.push(item);
+        if self.batch.len() >= self.batch_size {
+            self.send_batch();
+        }
+    }
+
+    /// Sends the remaining detections.
+    pub fn flush(&mut self) {
+        self.send_batch();
+    }
+
+    fn send_batch(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut self.batch);
+        if self.down_until.is_none_or(|t| Instant::now() >= t) {
+            let (body, content_type) = match self.format {
+                // HEC accepts multiple events concatenated in one request.
+                HttpFormat::SplunkHec => (
+                    batch
+                        .iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    "application/json",
+                ),
+                HttpFormat::Webhook => {
+                    (Value::Array(batch.clone()).to_string(), "application/json")
+                }
+            };
+            match self.post_with_retry(&body, content_type) {
+                Ok(()) => {
+                    self.sent += batch.len();
+                    self.down_until = None;
+                    return;
+                }
+                // the batch was rejected (ex: 400 Bad Request), so only this batch is spooled
+                Err((_, false)) => {}
+                Err((_, true)) => self.down_until = Some(Instant::now() + self.cooldown),
+            }
+        }
+        self.spool_batch(&batch);
+    }
+
+    // Returns the error message and whether the endpoint seems to be down.
+    fn post_with_retry(&self, body: &str, content_type: &str) -> Result<(), (String, bool)> {
+        let mut wait = RETRY_INTERVAL;
+        let mut attempt = 0;
+        loop {
+            match self.post(body, content_type) {
+                Ok(()) => return Ok(()),
+                Err((e, retryable)) => {
+                    if !retryable || attempt >= self.retries {
+                        return Err((e, retryable));
+                    }
+                }
+            }
+            sleep(wait);
+            wait *= 2;
+            attempt += 1;
+        }
+    }
+
+    // Returns the error message and whether the request should be retried.
+    fn post(&self, body: &str, content_type: &str) -> Result<(), (String, bool)> {
+        let mut req = self
+            .agent
+            .post(&self.url)
+            .header("Content-Type", content_type);
+        if let Some(auth) = &self.auth {
+            req = req.header("Authorization", auth);
+        }
+        match req.send(body) {
+            Ok(res) => {
+                let status = res.status().as_u16();
+                match status {
+                    200..=299 => Ok(()),
+                    429 | 500..=599 => Err((format!("HTTP status {}", status), true)),
+                    _ => Err((format!("HTTP status {}", status), false)),
+                }
+            }
+            Err(e) => Err((e.to_string(), true)),
+        }
+    }
+
+    // The batch is written at once, so it is counted as either spooled or dropped.
+    fn spool_batch(&mut self, batch: &[Value]) {
+        if self.spool.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.spool_path);
+            match file {
+                Ok(file) => self.spool = Some(file),
+                Err(_) => {
+                    self.dropped += batch.len();
+                    return;
+                }
+            }
+        }
+        let lines: String = batch.iter().map(|item| format!("{}\n", item)).collect();
+        match self
+            .spool
+            .as_mut()
+            .map(|spool| spool.write_all(lines.as_bytes()))
+        {
+            Some(Ok(())) => self.spooled += batch.len(),
+            _ => self.dropped += batch.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // A minimal HTTP server that returns the `statuses` in order and records the request headers and bodies.
+    fn serve(
+        listener: TcpListener,
+        statuses: Vec<u16>,
+    ) -> thread::JoinHandle<Vec<(String, String)>> {
+        thread::spawn(move || {
+            let mut received = vec![];
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut headers = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(len) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    headers.push_str(&line);
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let res = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                reader.get_mut().write_all(res.as_bytes()).unwrap();
+                received.push((headers, String::from_utf8(body).unwrap()));
+            }
+            received
+        })
+    }
+
+    fn options(url: String, format: &str, spool: PathBuf) -> HttpOutputOptions {
+        HttpOutputOptions {
+            http_url: Some(url),
+            http_format: format.to_string(),
+            http_token: Some("secret".to_string()),
+            http_batch_size: 2,
+            http_retries: 0,
+            http_spool: spool,
+            ..Default::default()
+        }
+    }
+
+    fn write_rules(sink: &mut HttpSink, count: usize) {
+        for i in 0..count {
+            sink.write(json!({"RuleTitle": format!("Rule {}", i)}), &json!({}));
+        }
+        sink.flush();
+    }
+
+    #[test]
+    fn test_http_sink_hec() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/services/collector/event",
+            listener.local_addr().unwrap()
+        );
+        let server = serve(listener, vec![200, 200]);
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = HttpSink::new(&options(url, "hec", dir.path().join("spool"))).unwrap();
+        let event = json!({"eventTime": "2024-08-18T13:05:25Z"});
+        for i in 0..3 {
+            sink.write(json!({"RuleTitle": format!("Rule {}", i)}), &event);
+        }
+        sink.flush();
+        let received = server.join().unwrap();
+        assert_eq!(sink.sent, 3);
+        assert_eq!(sink.spooled, 0);
+        assert!(received[0].0.contains("Splunk secret"));
+        let events: Vec<Value> = received[0]
+            .1
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["time"], 1723986325.0);
+        assert_eq!(events[1]["event"]["RuleTitle"], "Rule 1");
+    }
+
+    #[test]
+    fn test_http_sink_spool() {
+        // bind and drop a listener to get a port where nothing is listening
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let spool = dir.path().join("spool.jsonl");
+        let mut sink = HttpSink::new(&options(
+            format!("http://{}", addr),
+            "webhook",
+            spool.clone(),
+        ))
+        .unwrap();
+        write_rules(&mut sink, 3);
+        assert_eq!(sink.sent, 0);
+        assert_eq!(sink.spooled, 3);
+        assert_eq!(sink.dropped, 0);
+        assert_eq!(fs::read_to_string(spool).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_http_sink_unwritable_spool() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let spool = dir.path().join("missing").join("spool.jsonl");
+        let mut sink =
+            HttpSink::new(&options(format!("http://{}", addr), "webhook", spool)).unwrap();
+        write_rules(&mut sink, 3);
+        assert_eq!(sink.sent, 0);
+        assert_eq!(sink.spooled, 0);
+        assert_eq!(sink.dropped, 3);
+    }
+
+    #[test]
+    fn test_http_sink_rejected_batch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = serve(listener, vec![400, 200]);
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = HttpSink::new(&options(url, "webhook", dir.path().join("spool"))).unwrap();
+        write_rules(&mut sink, 4);
+        assert_eq!(server.join().unwrap().len(), 2);
+        assert_eq!(sink.sent, 2);
+        assert_eq!(sink.spooled, 2);
+    }
+
+    #[test]
+    fn test_http_sink_cooldown() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = serve(listener, vec![503]);
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = HttpSink::new(&options(url, "webhook", dir.path().join("spool"))).unwrap();
+        write_rules(&mut sink, 4);
+        // the second batch is spooled without sending while the endpoint is down
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert_eq!(sink.sent, 0);
+        assert_eq!(sink.spooled, 4);
+    }
+
+    #[test]
+    fn test_http_sink_retry_after_cooldown() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = serve(listener, vec![503, 200]);
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = HttpSink::new(&options(url, "webhook", dir.path().join("spool"))).unwrap();
+        sink.cooldown = Duration::ZERO;
+        write_rules(&mut sink, 4);
+        assert_eq!(server.join().unwrap().len(), 2);
+        assert_eq!(sink.sent, 2);
+        assert_eq!(sink.spooled, 2);
+    }
+}
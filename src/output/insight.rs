@@ -0,0 +1,150 @@
+use crate::core::color::SuzakuColor::Green;
+use crate::core::util::{get_json_str, get_json_value, p};
+use crate::option::timeformat::TimeFormatter;
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// An Insights event. The Start and End events share the same sharedEventID.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Insight {
+    insight_type: String,
+    event_source: String,
+    event_name: String,
+    error_code: Option<String>,
+    region: String,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    baseline_average: Option<f64>,
+    insight_average: Option<f64>,
+}
+
+/// Collects CloudTrail Insights events (unusual API call or error rates) from insightDetails.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InsightSummary {
+    insights: BTreeMap<String, Insight>,
+}
+
+impl InsightSummary {
+    pub fn is_empty(&self) -> bool {
+        self.insights.is_empty()
+    }
+
+    pub fn add(&mut self, json: &Value) {
+        let Some(details) = json.get("insightDetails") else {
+            return;
+        };
+        let Some(id) = get_json_str(json, "sharedEventID").or(get_json_str(json, "eventID")) else {
+            return;
+        };
+        let get = |key: &str| get_json_str(details, key).unwrap_or("-").to_string();
+        let average = |key: &str| {
+            get_json_value(
+                details,
+                &format!("insightContext.statistics.{}.average", key),
+            )
+            .and_then(|v| v.as_f64())
+        };
+        let insight = self.insights.entry(id.to_string()).or_default();
+        insight.insight_type = get("insightType");
+        insight.event_source = get("eventSource");
+        insight.event_name = get("eventName");
+        insight.error_code = get_json_str(details, "errorCode").map(str::to_string);
+        insight.region = get_json_str(json, "awsRegion").unwrap_or("-").to_string();
+        insight.baseline_average = average("baseline").or(insight.baseline_average);
+        insight.insight_average = average("insight").or(insight.insight_average);
+        let time = get_json_str(json, "eventTime").map(str::to_string);
+        match get_json_str(details, "state") {
+            Some("End") => insight.end_time = time,
+            _ => insight.start_time = time,
+        }
+    }
+
+    pub fn print_table(&self, no_color: bool, time_fmt: &TimeFormatter) {
+        let mut tb = Table::new();
+        tb.load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_header(vec![
+                "Start",
+                "End",
+                "Insight Type",
+                "Event Source",
+                "Event Name",
+                "Error Code",
+                "Baseline Avg",
+                "Insight Avg",
+                "Region",
+            ]);
+        let fmt_time = |t: &Option<String>| {
+            t.as_deref()
+                .map_or("-".to_string(), |t| time_fmt.format_str(t))
+        };
+        let fmt_avg = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.2}", v));
+        let mut insights: Vec<&Insight> = self.insights.values().collect();
+        insights.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+        for insight in insights {
+            tb.add_row(vec![
+                Cell::new(fmt_time(&insight.start_time)),
+                Cell::new(fmt_time(&insight.end_time)),
+                Cell::new(&insight.insight_type),
+                Cell::new(&insight.event_source),
+                Cell::new(&insight.event_name),
+                Cell::new(insight.error_code.as_deref().unwrap_or("-")),
+                Cell::new(fmt_avg(insight.baseline_average)),
+                Cell::new(fmt_avg(insight.insight_average)),
+                Cell::new(&insight.region),
+            ]);
+        }
+        p(Green.rdg(no_color), "Insights Events:", true);
+        p(None, &format!("{tb}"), true);
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_insight_summary() {
+        let event = |state: &str, time: &str| {
+            json!({
+                "eventTime": time,
+                "eventType": "AwsCloudTrailInsight",
+                "eventCategory": "Insight",
+                "awsRegion": "us-east-1",
+                "eventID": format!("id-{}", state),
+                "sharedEventID": "shared-1",
+                "insightDetails": {
+                    "state": state,
+                    "eventSource": "ec2.amazonaws.com",
+                    "eventName": "RunInstances",
+                    "insightType": "ApiCallRateInsight",
+                    "insightContext": {
+                        "statistics": {
+                            "baseline": {"average": 0.01},
+                            "insight": {"average": 12.5}
+                        }
+                    }
+                }
+            })
+        };
+        let mut summary = InsightSummary::default();
+        summary.add(&event("Start", "2024-08-18T13:00:00Z"));
+        summary.add(&event("End", "2024-08-18T13:10:00Z"));
+        summary.add(&json!({"eventName": "RunInstances", "eventID": "x"}));
+        assert_eq!(summary.insights.len(), 1);
+        let insight = &summary.insights["shared-1"];
+        assert_eq!(insight.start_time.as_deref(), Some("2024-08-18T13:00:00Z"));
+        assert_eq!(insight.end_time.as_deref(), Some("2024-08-18T13:10:00Z"));
+        assert_eq!(insight.event_name, "RunInstances");
+        assert_eq!(insight.insight_type, "ApiCallRateInsight");
+        assert_eq!(insight.insight_average, Some(12.5));
+        assert_eq!(insight.error_code, None);
+    }
+}
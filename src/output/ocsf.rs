@@ -0,0 +1,400 @@
+use crate::cmd::aws_detect::get_rule_value;
+use crate::core::aggregate::{AGGREGATION_COUNT, AGGREGATION_FIRST_TIME, AGGREGATION_LAST_TIME};
+use crate::core::util::{get_json_str, get_json_value};
+use crate::option::cli::VERSION;
+use chrono::DateTime;
+use serde_json::{Map, Value, json};
+use sigma_rust::Rule;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+const OCSF_VERSION: &str = "1.1.0";
+
+/// Writes each detection as an OCSF Detection Finding (class 2004) in JSONL format.
+/// Events without detections (`--all-events`) are written as API Activity (class 6003).
+pub struct OcsfWriter {
+    writer: BufWriter<Box<dyn Write>>,
+}
+
+impl OcsfWriter {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Ok(OcsfWriter {
+            writer: BufWriter::new(Box::new(File::create(path)?)),
+        })
+    }
+
+    pub fn write(&mut self, json: &Value, rule: Option<&Rule>, raw_output: bool) -> io::Result<()> {
+        let doc = match rule {
+            Some(rule) => detection_finding(json, rule, raw_output),
+            None => {
+                let mut activity = api_activity(json);
+                if raw_output {
+                    activity["raw_data"] = json!(json.to_string());
+                }
+                activity
+            }
+        };
+        writeln!(self.writer, "{}", doc)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn severity_id(level: &str) -> u8 {
+    match level {
+        "informational" => 1,
+        "low" => 2,
+        "medium" => 3,
+        "high" => 4,
+        "critical" => 5,
+        _ => 0,
+    }
+}
+
+fn activity(event_name: &str, read_only: Option<bool>) -> (u8, &'static str) {
+    const CREATE: [&str; 4] = ["Create", "Run", "Allocate", "Import"];
+    const READ: [&str; 5] = ["Get", "Describe", "List", "Lookup", "Head"];
+    const UPDATE: [&str; 8] = [
+        "Update", "Modify", "Put", "Set", "Attach", "Enable", "Disable", "Stop",
+    ];
+    const DELETE: [&str; 5] = ["Delete", "Remove", "Detach", "Terminate", "Revoke"];
+    if CREATE.iter().any(|p| event_name.starts_with(p)) {
+        (1, "Create")
+    } else if READ.iter().any(|p| event_name.starts_with(p)) || read_only == Some(true) {
+        (2, "Read")
+    } else if UPDATE.iter().any(|p| event_name.starts_with(p)) {
+        (3, "Update")
+    } else if DELETE.iter().any(|p| event_name.starts_with(p)) {
+        (4, "Delete")
+    } else {
+        (99, "Other")
+    }
+}
+
+fn epoch_millis(json: &Value, key: &str) -> Option<i64> {
+    get_json_str(json, key)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.timestamp_millis())
+}
+
+fn object(fields: Vec<(&str, Option<Value>)>) -> Value {
+    let map: Map<String, Value> = fields
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
+        .collect();
+    Value::Object(map)
+}
+
+fn actor(json: &Value) -> Value {
+    let get = |key: &str| get_json_value(json, key).cloned();
+    let user = object(vec![
+        ("type", get("userIdentity.type")),
+        ("name", get("userIdentity.userName")),
+        ("uid", get("userIdentity.principalId")),
+        ("uid_alt", get("userIdentity.arn")),
+        ("credential_uid", get("userIdentity.accessKeyId")),
+        (
+            "account",
+            get("userIdentity.accountId").map(|uid| json!({ "uid": uid })),
+        ),
+    ]);
+    let session = object(vec![
+        (
+            "issuer",
+            get("userIdentity.sessionContext.sessionIssuer.arn"),
+        ),
+        (
+            "created_time_dt",
+            get("userIdentity.sessionContext.attributes.creationDate"),
+        ),
+        (
+            "is_mfa",
+            get_json_str(
+                json,
+                "userIdentity.sessionContext.attributes.mfaAuthenticated",
+            )
+            .map(|v| json!(v == "true")),
+        ),
+    ]);
+    object(vec![
+        ("user", Some(user)),
+        ("session", Some(session).filter(|s| s != &json!({}))),
+        ("invoked_by", get("userIdentity.invokedBy")),
+    ])
+}
+
+fn api(json: &Value) -> Value {
+    let get = |key: &str| get_json_value(json, key).cloned();
+    object(vec![
+        ("operation", get("eventName")),
+        ("version", get("eventVersion")),
+        (
+            "service",
+            get("eventSource").map(|name| json!({ "name": name })),
+        ),
+        (
+            "request",
+            Some(object(vec![
+                ("uid", get("requestID")),
+                ("data", get("requestParameters").filter(|v| !v.is_null())),
+            ])),
+        ),
+        (
+            "response",
+            Some(object(vec![
+                ("error", get("errorCode")),
+                ("message", get("errorMessage")),
+                ("data", get("responseElements").filter(|v| !v.is_null())),
+            ])),
+        ),
+    ])
+}
+
+fn src_endpoint(json: &Value) -> Value {
+    match get_json_str(json, "sourceIPAddress") {
+        Some(addr) if addr.trim_start_matches("::ffff:").parse::<IpAddr>().is_ok() => {
+            json!({ "ip": addr.trim_start_matches("::ffff:") })
+        }
+        Some(addr) => json!({ "domain": addr }),
+        None => json!({}),
+    }
+}
+
+fn cloud(json: &Value) -> Value {
+    let get = |key: &str| get_json_value(json, key).cloned();
+    object(vec![
+        ("provider", Some(json!("AWS"))),
+        ("region", get("awsRegion")),
+        (
+            "account",
+            get("recipientAccountId").map(|uid| json!({ "uid": uid })),
+        ),
+    ])
+}
+
+/// Normalizes a CloudTrail record as an OCSF API Activity (class 6003) event.
+pub fn api_activity(json: &Value) -> Value {
+    let get = |key: &str| get_json_value(json, key).cloned();
+    let event_name = get_json_str(json, "eventName").unwrap_or_default();
+    let read_only = get_json_value(json, "readOnly").and_then(|v| v.as_bool());
+    let (activity_id, activity_name) = activity(event_name, read_only);
+    let failed = get_json_value(json, "errorCode").is_some();
+    let resources: Option<Value> = get_json_value(json, "resources")
+        .and_then(|r| r.as_array())
+        .map(|resources| {
+            resources
+                .iter()
+                .map(|r| {
+                    object(vec![
+                        ("uid", r.get("ARN").cloned()),
+                        ("type", r.get("type").cloned()),
+                        (
+                            "owner",
+                            r.get("accountId")
+                                .map(|uid| json!({ "account": { "uid": uid } })),
+                        ),
+                    ])
+                })
+                .collect::<Vec<Value>>()
+        })
+        .map(Value::Array);
+    object(vec![
+        ("class_uid", Some(json!(6003))),
+        ("class_name", Some(json!("API Activity"))),
+        ("category_uid", Some(json!(6))),
+        ("category_name", Some(json!("Application Activity"))),
+        ("activity_id", Some(json!(activity_id))),
+        ("activity_name", Some(json!(activity_name))),
+        ("type_uid", Some(json!(6003 * 100 + activity_id as u32))),
+        ("time", epoch_millis(json, "eventTime").map(|t| json!(t))),
+        ("severity_id", Some(json!(1))),
+        ("severity", Some(json!("Informational"))),
+        ("status_id", Some(json!(if failed { 2 } else { 1 }))),
+        (
+            "status",
+            Some(json!(if failed { "Failure" } else { "Success" })),
+        ),
+        ("status_code", get("errorCode")),
+        ("status_detail", get("errorMessage")),
+        (
+            "metadata",
+            Some(object(vec![
+                ("version", Some(json!(OCSF_VERSION))),
+                ("uid", get("eventID")),
+                ("event_code", get("eventType")),
+                (
+                    "product",
+                    Some(json!({
+                        "name": "CloudTrail",
+                        "vendor_name": "AWS",
+                        "version": get("eventVersion"),
+                    })),
+                ),
+            ])),
+        ),
+        ("actor", Some(actor(json))),
+        ("api", Some(api(json))),
+        ("src_endpoint", Some(src_endpoint(json))),
+        (
+            "http_request",
+            get("userAgent").map(|ua| json!({ "user_agent": ua })),
+        ),
+        ("cloud", Some(cloud(json))),
+        ("resources", resources),
+    ])
+}
+
+/// Creates an OCSF Detection Finding (class 2004) for a rule match.
+pub fn detection_finding(json: &Value, rule: &Rule, raw_output: bool) -> Value {
+    let level = get_rule_value("level", rule).unwrap_or_default();
+    let severity_id = severity_id(&level);
+    let severity = match severity_id {
+        0 => "Unknown".to_string(),
+        _ => {
+            let mut chars = level.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().collect::<String>() + chars.as_str())
+                .unwrap_or_default()
+        }
+    };
+    let event_id = get_json_str(json, "eventID").unwrap_or("-");
+    let rule_uid = rule.id.clone().unwrap_or(rule.title.clone());
+    let finding_info = object(vec![
+        ("uid", Some(json!(format!("{}-{}", event_id, rule_uid)))),
+        ("title", Some(json!(rule.title))),
+        ("desc", rule.description.clone().map(Value::String)),
+        ("types", rule.tags.as_ref().map(|t| json!(t))),
+        (
+            "src_url",
+            rule.references
+                .as_ref()
+                .and_then(|r| r.first())
+                .map(|r| json!(r)),
+        ),
+        (
+            "analytic",
+            Some(object(vec![
+                ("uid", rule.id.clone().map(Value::String)),
+                ("name", Some(json!(rule.title))),
+                ("type_id", Some(json!(1))),
+                ("type", Some(json!("Rule"))),
+            ])),
+        ),
+    ]);
+    let activity = api_activity(json);
+    let evidence = json!({
+        "actor": activity["actor"],
+        "api": activity["api"],
+        "src_endpoint": activity["src_endpoint"],
+        "data": activity,
+    });
+    object(vec![
+        ("class_uid", Some(json!(2004))),
+        ("class_name", Some(json!("Detection Finding"))),
+        ("category_uid", Some(json!(2))),
+        ("category_name", Some(json!("Findings"))),
+        ("activity_id", Some(json!(1))),
+        ("activity_name", Some(json!("Create"))),
+        ("type_uid", Some(json!(200401))),
+        ("time", epoch_millis(json, "eventTime").map(|t| json!(t))),
+        // aggregated detections (--aggregate-window)
+        ("count", get_json_value(json, AGGREGATION_COUNT).cloned()),
+        (
+            "start_time",
+            epoch_millis(json, AGGREGATION_FIRST_TIME).map(|t| json!(t)),
+        ),
+        (
+            "end_time",
+            epoch_millis(json, AGGREGATION_LAST_TIME).map(|t| json!(t)),
+        ),
+        ("severity_id", Some(json!(severity_id))),
+        ("severity", Some(json!(severity))),
+        ("status_id", Some(json!(1))),
+        ("status", Some(json!("New"))),
+        (
+            "metadata",
+            Some(json!({
+                "version": OCSF_VERSION,
+                "product": {
+                    "name": "Suzaku",
+                    "vendor_name": "Yamato Security",
+                    "version": VERSION,
+                },
+                "labels": rule.tags.clone().unwrap_or_default(),
+            })),
+        ),
+        ("finding_info", Some(finding_info)),
+        ("cloud", Some(cloud(json))),
+        ("evidences", Some(json!([evidence]))),
+        ("raw_data", raw_output.then(|| json!(json.to_string()))),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+
+    #[test]
+    fn test_detection_finding_fields() {
+        let finding = detection_finding(&test_event(), &test_rule("high"), false);
+        assert_eq!(finding["class_uid"], 2004);
+        assert_eq!(finding["type_uid"], 200401);
+        assert_eq!(finding["severity_id"], 4);
+        assert_eq!(finding["severity"], "High");
+        assert_eq!(finding["time"], 1723986325000_i64);
+        assert_eq!(finding["finding_info"]["uid"], "03383b06-test_rule");
+        assert_eq!(finding["finding_info"]["analytic"]["uid"], "test_rule");
+        assert_eq!(finding["cloud"]["account"]["uid"], "123456789012");
+        assert!(finding.get("raw_data").is_none());
+        assert!(finding.get("count").is_none());
+    }
+
+    #[test]
+    fn test_aggregated_detection_has_count_and_times() {
+        let mut event = test_event();
+        event["suzakuAggregation"] = json!({
+            "count": 3,
+            "firstEventTime": "2024-08-18T13:05:25Z",
+            "lastEventTime": "2024-08-18T13:15:25Z",
+        });
+        let finding = detection_finding(&event, &test_rule("high"), false);
+        assert_eq!(finding["count"], 3);
+        assert_eq!(finding["start_time"], 1723986325000_i64);
+        assert_eq!(finding["end_time"], 1723986925000_i64);
+    }
+
+    #[test]
+    fn test_detection_finding_evidence() {
+        let mut event = test_event();
+        event["errorCode"] = json!("AccessDenied");
+        let finding = detection_finding(&event, &test_rule("high"), false);
+        let activity = &finding["evidences"][0]["data"];
+        assert_eq!(activity["class_uid"], 6003);
+        assert_eq!(activity["activity_id"], 4);
+        assert_eq!(activity["type_uid"], 600304);
+        assert_eq!(activity["status"], "Failure");
+        assert_eq!(activity["api"]["operation"], "DeleteTrail");
+        assert_eq!(activity["src_endpoint"]["ip"], "192.0.2.1");
+        assert_eq!(activity["actor"]["user"]["name"], "alice");
+    }
+
+    #[test]
+    fn test_raw_output_adds_raw_data() {
+        let finding = detection_finding(&test_event(), &test_rule("high"), true);
+        assert!(finding["raw_data"].is_string());
+    }
+
+    #[test]
+    fn test_event_without_detection() {
+        let activity = api_activity(&json!({ "eventName": "ListBuckets" }));
+        assert_eq!(activity["class_uid"], 6003);
+        assert_eq!(activity["api"]["operation"], "ListBuckets");
+        assert!(activity.get("finding_info").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::core::util::get_json_str;
+use chrono::DateTime;
+use parquet::basic::{
+    Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType, ZstdLevel,
+};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use serde_json::Value;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+// Rows are buffered in memory and written as a row group when this many rows are collected.
+const ROW_GROUP_SIZE: usize = 50_000;
+
+/// Writes records in the Apache Parquet format (ZSTD compressed).
+/// The columns are `event_time` (timestamp), the profile fields and `raw_event` (the original JSON).
+pub struct ParquetWriter {
+    writer: SerializedFileWriter<File>,
+    event_times: Vec<Option<i64>>,
+    columns: Vec<Vec<Option<String>>>,
+    raw_events: Vec<String>,
+}
+
+impl ParquetWriter {
+    pub fn new(path: &Path, profile: &[(String, String)]) -> Result<Self> {
+        let mut fields = vec![Arc::new(
+            Type::primitive_type_builder("event_time", PhysicalType::INT64)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(Some(LogicalType::timestamp(true, TimeUnit::MICROS)))
+                .build()?,
+        )];
+        for (k, _) in profile {
+            fields.push(Arc::new(string_type(k, Repetition::OPTIONAL)?));
+        }
+        fields.push(Arc::new(string_type("raw_event", Repetition::REQUIRED)?));
+        let schema = Type::group_type_builder("detection")
+            .with_fields(fields)
+            .build()?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer =
+            SerializedFileWriter::new(File::create(path)?, Arc::new(schema), Arc::new(props))?;
+        Ok(ParquetWriter {
+            writer,
+            event_times: vec![],
+            columns: vec![vec![]; profile.len()],
+            raw_events: vec![],
+        })
+    }
+
+    pub fn write(&mut self, record: &[String], json: &Value) -> Result<()> {
+        let event_time = get_json_str(json, "eventTime")
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_micros());
+        self.event_times.push(event_time);
+        for (column, value) in self.columns.iter_mut().zip(record) {
+            // "-" (field not found) and "" (no rule information) are stored as null
+            let value = (!value.is_empty() && value != "-").then(|| value.clone());
+            column.push(value);
+        }
+        self.raw_events.push(json.to_string());
+        if self.raw_events.len() >= ROW_GROUP_SIZE {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> Result<()> {
+        if self.raw_events.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        if let Some(mut column) = row_group.next_column()? {
+            let (values, def_levels) = optional_values(self.event_times.drain(..));
+            column
+                .typed::<Int64Type>()
+                .write_batch(&values, Some(&def_levels), None)?;
+            column.close()?;
+        }
+        for profile_column in self.columns.iter_mut() {
+            if let Some(mut column) = row_group.next_column()? {
+                let (values, def_levels) = optional_values(
+                    profile_column
+                        .drain(..)
+                        .map(|v| v.map(|v| ByteArray::from(v.into_bytes()))),
+                );
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&def_levels), None)?;
+                column.close()?;
+            }
+        }
+        if let Some(mut column) = row_group.next_column()? {
+            let values: Vec<ByteArray> = self
+                .raw_events
+                .drain(..)
+                .map(|v| ByteArray::from(v.into_bytes()))
+                .collect();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&values, None, None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the file footer.
+    pub fn finish(&mut self) -> Result<()> {
+        self.write_row_group()?;
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+fn string_type(name: &str, repetition: Repetition) -> Result<Type> {
+    Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+        .with_repetition(repetition)
+        .with_logical_type(Some(LogicalType::String))
+        .build()
+}
+
+// Splits optional values into the non-null values and the definition levels.
+fn optional_values<T>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+    let mut non_null = vec![];
+    let mut def_levels = vec![];
+    for value in values {
+        match value {
+            Some(v) => {
+                non_null.push(v);
+                def_levels.push(1);
+            }
+            None => def_levels.push(0),
+        }
+    }
+    (non_null, def_levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::{Field, Row, RowAccessor};
+    use serde_json::json;
+
+    // Writes the (record, event) rows with a RuleTitle and AWS-Region profile and reads them back.
+    fn write_and_read(rows: &[(Vec<&str>, Value)]) -> SerializedFileReader<File> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.parquet");
+        let profile = vec![
+            ("RuleTitle".to_string(), "sigma.title".to_string()),
+            ("AWS-Region".to_string(), ".awsRegion".to_string()),
+        ];
+        let mut writer = ParquetWriter::new(&path, &profile).unwrap();
+        for (record, json) in rows {
+            let record: Vec<String> = record.iter().map(|v| v.to_string()).collect();
+            writer.write(&record, json).unwrap();
+        }
+        writer.finish().unwrap();
+        SerializedFileReader::new(File::open(&path).unwrap()).unwrap()
+    }
+
+    fn read_rows(reader: &SerializedFileReader<File>) -> Vec<Row> {
+        reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_columns() {
+        let reader = write_and_read(&[]);
+        let columns: Vec<String> = reader
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert_eq!(
+            columns,
+            vec!["event_time", "RuleTitle", "AWS-Region", "raw_event"]
+        );
+    }
+
+    #[test]
+    fn test_event_time_is_timestamp() {
+        let json = json!({"eventTime": "2024-08-18T13:05:25Z", "awsRegion": "us-east-1"});
+        let reader = write_and_read(&[(vec!["Test Rule", "us-east-1"], json)]);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        let rows = read_rows(&reader);
+        assert_eq!(rows[0].get_timestamp_micros(0).unwrap(), 1723986325000000);
+        assert_eq!(rows[0].get_string(1).unwrap(), "Test Rule");
+    }
+
+    #[test]
+    fn test_missing_values_are_null() {
+        let reader = write_and_read(&[(vec!["", "-"], json!({}))]);
+        let rows = read_rows(&reader);
+        assert!(matches!(
+            rows[0].get_column_iter().next(),
+            Some((_, Field::Null))
+        ));
+        assert!(matches!(
+            rows[0].get_column_iter().nth(1),
+            Some((_, Field::Null))
+        ));
+        assert_eq!(rows[0].get_string(3).unwrap(), "{}");
+    }
+}
@@ -0,0 +1,173 @@
+use crate::core::color::SuzakuColor::Green;
+use crate::core::rules::level_to_int;
+use crate::core::util::{get_json_value, p};
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use serde_json::Value;
+use sigma_rust::Rule;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const PIVOT_CONFIG_PATH: &str = "config/pivot_keywords.txt";
+
+/// Loads the `Category.field.path` lines of the pivot keywords config.
+pub fn load_config(path: &str) -> Vec<(String, String)> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('.'))
+        .map(|(category, field)| (category.to_string(), field.to_string()))
+        .collect()
+}
+
+/// The file for a category (ex: output.txt -> output-Users.txt).
+pub fn output_path(base: &Path, category: &str) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = base
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or("txt".to_string());
+    base.with_file_name(format!("{}-{}.{}", stem, category, ext))
+}
+
+/// Collects the unique values of the configured fields from detections at or above a level.
+pub struct PivotKeywords {
+    min_level: u8,
+    fields: Vec<(String, String)>,
+    keywords: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl PivotKeywords {
+    pub fn new(fields: Vec<(String, String)>, min_level: &str) -> Self {
+        let keywords = fields
+            .iter()
+            .map(|(category, _)| (category.clone(), BTreeSet::new()))
+            .collect();
+        PivotKeywords {
+            min_level: level_to_int(min_level),
+            fields,
+            keywords,
+        }
+    }
+
+    pub fn add(&mut self, json: &Value, rule: &Rule) {
+        let level = rule
+            .level
+            .as_ref()
+            .map(|l| level_to_int(&format!("{:?}", l)))
+            .unwrap_or(0);
+        if level < self.min_level {
+            return;
+        }
+        for (category, field) in &self.fields {
+            let value = match get_json_value(json, field) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Number(n)) => n.to_string(),
+                Some(Value::Bool(b)) => b.to_string(),
+                _ => continue,
+            };
+            if value.is_empty() || value == "-" {
+                continue;
+            }
+            if let Some(values) = self.keywords.get_mut(category) {
+                values.insert(value);
+            }
+        }
+    }
+
+    /// Writes a text file per category and returns the paths.
+    pub fn write(&self, base: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for (category, values) in &self.keywords {
+            let path = output_path(base, category);
+            let mut writer = BufWriter::new(fs::File::create(&path)?);
+            for value in values {
+                writeln!(writer, "{}", value)?;
+            }
+            writer.flush()?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    pub fn print_table(&self, no_color: bool) {
+        let mut tb = Table::new();
+        tb.load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_header(vec!["Category", "Keywords"]);
+        for (category, values) in &self.keywords {
+            let keywords = if values.is_empty() {
+                "-".to_string()
+            } else {
+                values.iter().cloned().collect::<Vec<_>>().join("\n")
+            };
+            tb.add_row(vec![
+                Cell::new(format!("{} ({})", category, values.len())),
+                Cell::new(keywords),
+            ]);
+        }
+        p(Green.rdg(no_color), "Pivot Keywords:", true);
+        p(None, &format!("{tb}"), true);
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+    use serde_json::json;
+
+    fn test_pivot() -> PivotKeywords {
+        let fields = vec![
+            ("IP-Addresses".to_string(), "sourceIPAddress".to_string()),
+            ("Users".to_string(), "userIdentity.arn".to_string()),
+            ("Users".to_string(), "userIdentity.userName".to_string()),
+            ("Regions".to_string(), "awsRegion".to_string()),
+        ];
+        PivotKeywords::new(fields, "high")
+    }
+
+    #[test]
+    fn test_keywords_are_unique() {
+        let mut pivot = test_pivot();
+        pivot.add(&test_event(), &test_rule("critical"));
+        pivot.add(&test_event(), &test_rule("high"));
+        assert_eq!(
+            pivot.keywords["Users"],
+            BTreeSet::from([
+                "alice".to_string(),
+                "arn:aws:iam::123456789012:user/alice".to_string()
+            ])
+        );
+        assert_eq!(pivot.keywords["IP-Addresses"].len(), 1);
+    }
+
+    #[test]
+    fn test_detections_under_min_level_are_skipped() {
+        let mut pivot = test_pivot();
+        pivot.add(
+            &json!({"sourceIPAddress": "198.51.100.1"}),
+            &test_rule("medium"),
+        );
+        assert!(pivot.keywords["IP-Addresses"].is_empty());
+    }
+
+    #[test]
+    fn test_write_file_per_category() {
+        let mut pivot = test_pivot();
+        pivot.add(&json!({"sourceIPAddress": "192.0.2.1"}), &test_rule("high"));
+        let dir = tempfile::tempdir().unwrap();
+        let paths = pivot.write(&dir.path().join("pivot.txt")).unwrap();
+        assert_eq!(paths.len(), 3);
+        let ips = fs::read_to_string(dir.path().join("pivot-IP-Addresses.txt")).unwrap();
+        assert_eq!(ips, "192.0.2.1\n");
+        let regions = fs::read_to_string(dir.path().join("pivot-Regions.txt")).unwrap();
+        assert!(regions.is_empty());
+    }
+}
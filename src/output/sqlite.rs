@@ -0,0 +1,228 @@
+use crate::cmd::aws_detect::get_rule_value;
+use crate::core::util::get_json_str;
+use rusqlite::{Connection, params, params_from_iter};
+use serde_json::Value;
+use sigma_rust::Rule;
+use std::path::Path;
+
+// Rows are committed in batches to keep inserts fast.
+const BATCH_SIZE: usize = 10_000;
+
+const RULE_COLUMNS: [&str; 11] = [
+    "id",
+    "title",
+    "level",
+    "status",
+    "author",
+    "description",
+    "references",
+    "tags",
+    "falsepositives",
+    "date",
+    "modified",
+];
+
+pub struct SqliteWriter {
+    conn: Connection,
+    insert_sql: String,
+    pending: usize,
+}
+
+impl SqliteWriter {
+    pub fn new(path: &Path, profile: &[(String, String)]) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = MEMORY; PRAGMA synchronous = OFF;")?;
+        // the tables of an existing database are replaced (-C, --clobber)
+        conn.execute_batch("DROP TABLE IF EXISTS detections; DROP TABLE IF EXISTS rules;")?;
+
+        let profile_columns: Vec<String> = profile.iter().map(|(k, _)| quote(k)).collect();
+        let create_columns: Vec<String> = profile_columns
+            .iter()
+            .map(|c| format!("{} TEXT", c))
+            .collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE detections (
+                id INTEGER PRIMARY KEY,
+                event_time TEXT,
+                rule_id TEXT,
+                principal_arn TEXT,
+                source_ip TEXT,
+                {},
+                raw_event TEXT
+            );",
+            create_columns.join(",\n")
+        ))?;
+        let rule_columns: Vec<String> = RULE_COLUMNS.iter().map(|c| quote(c)).collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE rules ({} TEXT PRIMARY KEY, {} TEXT);",
+            rule_columns[0],
+            rule_columns[1..].join(" TEXT, ")
+        ))?;
+
+        let placeholders = vec!["?"; profile.len() + 5].join(", ");
+        let insert_sql = format!(
+            "INSERT INTO detections (event_time, rule_id, principal_arn, source_ip, {}, raw_event) VALUES ({})",
+            profile_columns.join(", "),
+            placeholders
+        );
+        conn.execute_batch("BEGIN")?;
+        Ok(SqliteWriter {
+            conn,
+            insert_sql,
+            pending: 0,
+        })
+    }
+
+    pub fn insert_rules(&mut self, rules: &[&Rule]) -> rusqlite::Result<()> {
+        let columns: Vec<String> = RULE_COLUMNS.iter().map(|c| quote(c)).collect();
+        let sql = format!(
+            "INSERT OR IGNORE INTO rules ({}) VALUES ({})",
+            columns.join(", "),
+            vec!["?"; columns.len()].join(", ")
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        for rule in rules {
+            let values: Vec<Option<String>> = RULE_COLUMNS
+                .iter()
+                .map(|c| match *c {
+                    // rules without an id are keyed by their title
+                    "id" => rule.id.clone().or(Some(rule.title.clone())),
+                    _ => get_rule_value(c, rule),
+                })
+                .collect();
+            stmt.execute(params_from_iter(values))?;
+        }
+        Ok(())
+    }
+
+    /// Inserts an event. The rule_id is NULL for events without detections (`--all-events`).
+    pub fn insert(
+        &mut self,
+        record: &[String],
+        json: &Value,
+        rule: Option<&Rule>,
+    ) -> rusqlite::Result<()> {
+        let get = |key: &str| get_json_str(json, key).map(|s| s.to_string());
+        let rule_id = rule.map(|rule| rule.id.clone().unwrap_or(rule.title.clone()));
+        let mut values: Vec<Option<String>> = vec![
+            get("eventTime"),
+            rule_id,
+            get("userIdentity.arn"),
+            get("sourceIPAddress"),
+        ];
+        values.extend(record.iter().map(|v| Some(v.clone())));
+        values.push(Some(json.to_string()));
+        self.conn
+            .prepare_cached(&self.insert_sql)?
+            .execute(params_from_iter(values))?;
+        self.pending += 1;
+        if self.pending >= BATCH_SIZE {
+            self.conn.execute_batch("COMMIT; BEGIN")?;
+            self.pending = 0;
+        }
+        Ok(())
+    }
+
+    /// Commits the pending rows so other processes can read them (ex: with --follow).
+    pub fn commit(&mut self) -> rusqlite::Result<()> {
+        if self.pending > 0 {
+            self.conn.execute_batch("COMMIT; BEGIN")?;
+            self.pending = 0;
+        }
+        Ok(())
+    }
+
+    /// Commits the remaining rows and creates the indexes.
+    pub fn finish(&mut self) -> rusqlite::Result<()> {
+        self.conn.execute_batch(
+            "COMMIT;
+            CREATE INDEX idx_detections_event_time ON detections (event_time);
+            CREATE INDEX idx_detections_principal_arn ON detections (principal_arn);
+            CREATE INDEX idx_detections_source_ip ON detections (source_ip);
+            CREATE INDEX idx_detections_rule_id ON detections (rule_id);",
+        )?;
+        self.conn.execute("ANALYZE", params![])?;
+        Ok(())
+    }
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+    use serde_json::json;
+
+    fn profile() -> Vec<(String, String)> {
+        vec![
+            ("Timestamp".to_string(), ".eventTime".to_string()),
+            ("RuleTitle".to_string(), "sigma.title".to_string()),
+        ]
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_detections_join_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let rule = test_rule("high");
+        let mut writer = SqliteWriter::new(&path, &profile()).unwrap();
+        writer.insert_rules(&[&rule]).unwrap();
+        let record = vec![
+            "2024-08-18 13:05:25".to_string(),
+            "CloudTrail Deleted".to_string(),
+        ];
+        writer.insert(&record, &test_event(), Some(&rule)).unwrap();
+        writer.finish().unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let (arn, title, level): (String, String, String) = conn
+            .query_row(
+                "SELECT d.principal_arn, r.title, r.level FROM detections d JOIN rules r ON d.rule_id = r.id WHERE d.source_ip = '192.0.2.1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(arn, "arn:aws:iam::123456789012:user/alice");
+        assert_eq!(title, "CloudTrail Deleted");
+        assert_eq!(level, "high");
+    }
+
+    #[test]
+    fn test_events_without_detections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut writer = SqliteWriter::new(&path, &profile()).unwrap();
+        let record = vec!["2024-08-18 13:06:00".to_string(), "".to_string()];
+        writer.insert(&record, &json!({}), None).unwrap();
+        writer.finish().unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let sql = "SELECT COUNT(*) FROM detections WHERE rule_id IS NULL";
+        assert_eq!(count(&conn, sql), 1);
+    }
+
+    #[test]
+    fn test_existing_database_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let rule = test_rule("high");
+        let record = vec!["2024-08-18 13:05:25".to_string(), "".to_string()];
+        for _ in 0..2 {
+            let mut writer = SqliteWriter::new(&path, &profile()).unwrap();
+            writer.insert_rules(&[&rule]).unwrap();
+            writer.insert(&record, &test_event(), Some(&rule)).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM detections"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM rules"), 1);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::core::util::get_json_str;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value, json};
+use sigma_rust::Rule;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+// Namespace for the deterministic identifiers of STIX Cyber-observable Objects (STIX 2.1 section 2.9).
+const SCO_NAMESPACE: Uuid = Uuid::from_u128(0x00abedb4_aa42_466c_9c01_fed23315a9b7);
+// Namespace for the identifiers of the objects created by Suzaku.
+const SUZAKU_NAMESPACE: Uuid = Uuid::from_u128(0x4f6b3c2e_8a1d_5e7f_9b0c_1d2e3f4a5b6c);
+
+struct RuleSighting {
+    indicator_id: String,
+    first_seen: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+    count: usize,
+    observables: BTreeSet<String>,
+}
+
+/// Collects detections and exports them as a STIX 2.1 bundle.
+/// Each matched rule becomes an `indicator` (Sigma pattern) with a `sighting` that refers to an
+/// `observed-data` object with the source IPs, user accounts, access keys and user agents.
+pub struct StixBundle {
+    created: String,
+    identity_id: String,
+    rule_paths: HashMap<String, PathBuf>,
+    indicators: BTreeMap<String, Value>,
+    sightings: BTreeMap<String, RuleSighting>,
+    observables: BTreeMap<String, Value>,
+}
+
+impl StixBundle {
+    /// `rule_paths` maps the rule id (or title) to the YAML file of the rule.
+    pub fn new(rule_paths: HashMap<String, PathBuf>) -> Self {
+        StixBundle {
+            created: timestamp(&Utc::now()),
+            identity_id: format!("identity--{}", Uuid::new_v5(&SUZAKU_NAMESPACE, b"suzaku")),
+            rule_paths,
+            indicators: BTreeMap::new(),
+            sightings: BTreeMap::new(),
+            observables: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, json: &Value, rule: &Rule) {
+        let key = rule_key(rule);
+        if !self.indicators.contains_key(&key) {
+            let indicator = self.indicator(rule, &key);
+            self.indicators.insert(key.clone(), indicator);
+        }
+        let observables: Vec<Value> = extract_observables(json);
+        let event_time = get_json_str(json, "eventTime")
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let indicator_id = self.indicators[&key]["id"].as_str().unwrap_or_default();
+        let sighting = self.sightings.entry(key).or_insert_with(|| RuleSighting {
+            indicator_id: indicator_id.to_string(),
+            first_seen: None,
+            last_seen: None,
+            count: 0,
+            observables: BTreeSet::new(),
+        });
+        sighting.count += 1;
+        if let Some(time) = event_time {
+            sighting.first_seen = Some(sighting.first_seen.map_or(time, |t| t.min(time)));
+            sighting.last_seen = Some(sighting.last_seen.map_or(time, |t| t.max(time)));
+        }
+        for observable in observables {
+            let id = observable["id"].as_str().unwrap_or_default().to_string();
+            sighting.observables.insert(id.clone());
+            self.observables.entry(id).or_insert(observable);
+        }
+    }
+
+    fn indicator(&self, rule: &Rule, key: &str) -> Value {
+        let level = rule
+            .level
+            .as_ref()
+            .map(|l| format!("{:?}", l).to_lowercase());
+        // the YAML is read only for the matched rules
+        let pattern = self
+            .rule_paths
+            .get(key)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let mut indicator = json!({
+            "type": "indicator",
+            "spec_version": "2.1",
+            "id": format!("indicator--{}", Uuid::new_v5(&SUZAKU_NAMESPACE, key.as_bytes())),
+            "created_by_ref": self.identity_id,
+            "created": self.created,
+            "modified": self.created,
+            "name": rule.title,
+            "indicator_types": ["anomalous-activity"],
+            "pattern_type": "sigma",
+            "pattern": pattern,
+            "valid_from": self.created,
+        });
+        if let Some(description) = &rule.description {
+            indicator["description"] = json!(description);
+        }
+        let mut labels: Vec<String> = level.into_iter().collect();
+        labels.extend(rule.tags.clone().unwrap_or_default());
+        if !labels.is_empty() {
+            indicator["labels"] = json!(labels);
+        }
+        if let Some(references) = &rule.references {
+            let refs: Vec<Value> = references
+                .iter()
+                .map(|r| json!({ "source_name": "reference", "url": r }))
+                .collect();
+            indicator["external_references"] = Value::Array(refs);
+        }
+        indicator
+    }
+
+    fn to_bundle(&self) -> Value {
+        let mut objects = vec![json!({
+            "type": "identity",
+            "spec_version": "2.1",
+            "id": self.identity_id,
+            "created": self.created,
+            "modified": self.created,
+            "name": "Suzaku",
+            "identity_class": "system",
+        })];
+        objects.extend(self.indicators.values().cloned());
+        objects.extend(self.observables.values().cloned());
+        for (key, sighting) in &self.sightings {
+            let first = sighting.first_seen.as_ref().map(timestamp);
+            let last = sighting.last_seen.as_ref().map(timestamp);
+            let observed_id = format!(
+                "observed-data--{}",
+                Uuid::new_v5(
+                    &SUZAKU_NAMESPACE,
+                    format!("observed-data/{}", key).as_bytes()
+                )
+            );
+            let mut sighting_obj = json!({
+                "type": "sighting",
+                "spec_version": "2.1",
+                "id": format!("sighting--{}", Uuid::new_v5(&SUZAKU_NAMESPACE, format!("sighting/{}", key).as_bytes())),
+                "created_by_ref": self.identity_id,
+                "created": self.created,
+                "modified": self.created,
+                "count": sighting.count,
+                "sighting_of_ref": sighting.indicator_id,
+                "where_sighted_refs": [self.identity_id],
+            });
+            if let (Some(first), Some(last)) = (&first, &last) {
+                sighting_obj["first_seen"] = json!(first);
+                sighting_obj["last_seen"] = json!(last);
+            }
+            // observed-data needs at least one object reference
+            if !sighting.observables.is_empty() {
+                objects.push(json!({
+                    "type": "observed-data",
+                    "spec_version": "2.1",
+                    "id": observed_id,
+                    "created_by_ref": self.identity_id,
+                    "created": self.created,
+                    "modified": self.created,
+                    "first_observed": first.clone().unwrap_or(self.created.clone()),
+                    "last_observed": last.clone().unwrap_or(self.created.clone()),
+                    "number_observed": sighting.count,
+                    "object_refs": sighting.observables,
+                }));
+                sighting_obj["observed_data_refs"] = json!([observed_id]);
+            }
+            objects.push(sighting_obj);
+        }
+        json!({
+            "type": "bundle",
+            "id": format!("bundle--{}", Uuid::new_v4()),
+            "objects": objects,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_bundle())?)
+    }
+}
+
+pub fn rule_key(rule: &Rule) -> String {
+    rule.id.clone().unwrap_or(rule.title.clone())
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// Creates a Cyber-observable Object with a deterministic id based on its contributing properties.
+fn observable(obj_type: &str, id_props: Value, extra: Value) -> Value {
+    let id = Uuid::new_v5(&SCO_NAMESPACE, id_props.to_string().as_bytes());
+    let mut obj = Map::new();
+    obj.insert("type".to_string(), json!(obj_type));
+    obj.insert("spec_version".to_string(), json!("2.1"));
+    obj.insert("id".to_string(), json!(format!("{}--{}", obj_type, id)));
+    for props in [id_props, extra] {
+        if let Value::Object(props) = props {
+            obj.extend(props);
+        }
+    }
+    Value::Object(obj)
+}
+
+fn extract_observables(json: &Value) -> Vec<Value> {
+    let get = |key: &str| get_json_str(json, key).filter(|v| !v.is_empty());
+    let mut observables = vec![];
+    // sourceIPAddress can also be an AWS service name (ex: cloudtrail.amazonaws.com)
+    if let Some(ip) = get("sourceIPAddress").and_then(|ip| ip.parse::<IpAddr>().ok()) {
+        let obj_type = if ip.is_ipv4() {
+            "ipv4-addr"
+        } else {
+            "ipv6-addr"
+        };
+        observables.push(observable(
+            obj_type,
+            json!({ "value": ip.to_string() }),
+            json!({}),
+        ));
+    }
+    if let Some(arn) = get("userIdentity.arn") {
+        let mut extra = Map::new();
+        if let Some(name) = get("userIdentity.userName") {
+            extra.insert("account_login".to_string(), json!(name));
+        }
+        if let Some(account_id) = get("userIdentity.accountId") {
+            extra.insert("x_aws_account_id".to_string(), json!(account_id));
+        }
+        if let Some(user_type) = get("userIdentity.type") {
+            extra.insert("x_aws_identity_type".to_string(), json!(user_type));
+        }
+        observables.push(observable(
+            "user-account",
+            json!({ "user_id": arn }),
+            Value::Object(extra),
+        ));
+    }
+    if let Some(key) = get("userIdentity.accessKeyId") {
+        let mut extra = json!({ "display_name": "AWS access key" });
+        if let Some(arn) = get("userIdentity.arn") {
+            extra["x_aws_principal_arn"] = json!(arn);
+        }
+        observables.push(observable("user-account", json!({ "user_id": key }), extra));
+    }
+    // STIX has no user agent object, so it is exported as software
+    if let Some(user_agent) = get("userAgent") {
+        observables.push(observable(
+            "software",
+            json!({ "name": user_agent }),
+            json!({ "x_suzaku_observable_type": "user-agent" }),
+        ));
+    }
+    observables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule, test_rule_yaml};
+    use tempfile::TempDir;
+
+    // Adds the test event twice, the second time an hour later from an AWS service.
+    fn test_bundle() -> (Value, TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rule.yml");
+        fs::write(&path, test_rule_yaml("high")).unwrap();
+        let mut bundle = StixBundle::new(HashMap::from([("test_rule".to_string(), path)]));
+        let rule = test_rule("high");
+        let mut event = test_event();
+        bundle.add(&event, &rule);
+        event["eventTime"] = json!("2024-08-18T14:05:25Z");
+        event["sourceIPAddress"] = json!("cloudtrail.amazonaws.com");
+        bundle.add(&event, &rule);
+        (bundle.to_bundle(), dir)
+    }
+
+    fn of_type<'a>(bundle: &'a Value, t: &str) -> Vec<&'a Value> {
+        bundle["objects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|o| o["type"] == t)
+            .collect()
+    }
+
+    #[test]
+    fn test_indicator_has_sigma_pattern() {
+        let (bundle, _dir) = test_bundle();
+        let indicators = of_type(&bundle, "indicator");
+        assert_eq!(indicators.len(), 1);
+        assert_eq!(indicators[0]["pattern_type"], "sigma");
+        assert_eq!(indicators[0]["labels"][0], "high");
+        assert!(
+            indicators[0]["pattern"]
+                .as_str()
+                .unwrap()
+                .contains("DeleteTrail")
+        );
+    }
+
+    #[test]
+    fn test_observables() {
+        let (bundle, _dir) = test_bundle();
+        // AWS service names are not IP addresses
+        assert_eq!(of_type(&bundle, "ipv4-addr").len(), 1);
+        assert_eq!(of_type(&bundle, "user-account").len(), 2);
+        assert_eq!(of_type(&bundle, "software").len(), 1);
+        let observed = of_type(&bundle, "observed-data")[0];
+        assert_eq!(observed["object_refs"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_sighting() {
+        let (bundle, _dir) = test_bundle();
+        let sighting = of_type(&bundle, "sighting")[0];
+        assert_eq!(sighting["count"], 2);
+        assert_eq!(sighting["first_seen"], "2024-08-18T13:05:25.000Z");
+        assert_eq!(sighting["last_seen"], "2024-08-18T14:05:25.000Z");
+        assert_eq!(
+            sighting["sighting_of_ref"],
+            of_type(&bundle, "indicator")[0]["id"]
+        );
+        let observed = of_type(&bundle, "observed-data")[0];
+        assert_eq!(sighting["observed_data_refs"][0], observed["id"]);
+    }
+}
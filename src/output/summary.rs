@@ -0,0 +1,112 @@
+use crate::cmd::aws_detect::DetectionSummary;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const LEVELS: [&str; 5] = ["critical", "high", "medium", "low", "informational"];
+
+#[derive(Serialize)]
+struct DataReduction {
+    events: usize,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct LevelHits {
+    total: usize,
+    unique: usize,
+}
+
+#[derive(Serialize)]
+struct SummaryOutput<'a> {
+    #[serde(flatten)]
+    summary: &'a DetectionSummary,
+    data_reduction: DataReduction,
+    level_counts: BTreeMap<&'static str, LevelHits>,
+}
+
+pub fn summary_json(sum: &DetectionSummary) -> Value {
+    let reduction = sum.total_events - sum.event_with_hits;
+    let percent = if sum.total_events == 0 {
+        0.0
+    } else {
+        reduction as f64 * 100.0 / sum.total_events as f64
+    };
+    let level_counts = LEVELS
+        .iter()
+        .map(|level| {
+            let hits = sum.level_with_hits.get(*level);
+            let counts = LevelHits {
+                total: hits.map(|h| h.values().sum()).unwrap_or(0),
+                unique: hits.map(|h| h.len()).unwrap_or(0),
+            };
+            (*level, counts)
+        })
+        .collect();
+    let output = SummaryOutput {
+        summary: sum,
+        data_reduction: DataReduction {
+            events: reduction,
+            percent,
+        },
+        level_counts,
+    };
+    serde_json::to_value(output).unwrap_or_default()
+}
+
+/// Writes the results summary of aws-ct-timeline as a JSON file.
+pub fn write_summary_json(path: &Path, sum: &DetectionSummary) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(&summary_json(sum))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_summary() -> DetectionSummary {
+        let mut sum = DetectionSummary {
+            total_events: 4,
+            event_with_hits: 1,
+            first_event_time: "2024-08-18T13:05:25Z".parse().ok(),
+            ..Default::default()
+        };
+        sum.level_with_hits
+            .entry("high".to_string())
+            .or_default()
+            .insert("Test Rule".to_string(), 3);
+        sum.author_titles
+            .entry("tester".to_string())
+            .or_default()
+            .insert("Test Rule".to_string());
+        sum
+    }
+
+    #[test]
+    fn test_data_reduction() {
+        let json = summary_json(&test_summary());
+        assert_eq!(json["total_events"], 4);
+        assert_eq!(json["data_reduction"]["events"], 3);
+        assert_eq!(json["data_reduction"]["percent"], 75.0);
+    }
+
+    #[test]
+    fn test_level_counts() {
+        let json = summary_json(&test_summary());
+        assert_eq!(json["level_counts"]["high"]["total"], 3);
+        assert_eq!(json["level_counts"]["high"]["unique"], 1);
+        assert_eq!(json["level_counts"]["low"]["total"], 0);
+        assert_eq!(json["level_with_hits"]["high"]["Test Rule"], 3);
+    }
+
+    #[test]
+    fn test_authors_and_event_times() {
+        let json = summary_json(&test_summary());
+        assert_eq!(json["author_titles"]["tester"][0], "Test Rule");
+        assert_eq!(json["first_event_time"], "2024-08-18T13:05:25Z");
+        assert!(json["last_event_time"].is_null());
+        assert!(json.get("timestamps").is_none());
+    }
+}
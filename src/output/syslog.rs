@@ -0,0 +1,333 @@
+use crate::core::util::get_json_str;
+use crate::option::cli::VERSION;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use sigma_rust::Rule;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::path::PathBuf;
+
+const APP_NAME: &str = "suzaku";
+// user-level messages
+const FACILITY: u8 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum SyslogTarget {
+    Udp(String),
+    Tcp(String),
+    File(PathBuf),
+}
+
+impl SyslogTarget {
+    /// Parses `udp://HOST:PORT`, `tcp://HOST:PORT` or a file path.
+    pub fn parse(target: &str) -> Self {
+        if let Some(addr) = target.strip_prefix("udp://") {
+            SyslogTarget::Udp(addr.to_string())
+        } else if let Some(addr) = target.strip_prefix("tcp://") {
+            SyslogTarget::Tcp(addr.to_string())
+        } else {
+            SyslogTarget::File(PathBuf::from(target))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyslogFormat {
+    Cef,
+    Leef,
+}
+
+impl SyslogFormat {
+    pub fn from_name(format: &str) -> Self {
+        if format.eq_ignore_ascii_case("leef") {
+            SyslogFormat::Leef
+        } else {
+            SyslogFormat::Cef
+        }
+    }
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    Tcp(String, TcpStream),
+    File(BufWriter<File>),
+}
+
+/// Sends each detection as an RFC 5424 syslog message with a CEF or LEEF payload.
+pub struct SyslogWriter {
+    transport: Transport,
+    format: SyslogFormat,
+    pub failed: usize,
+}
+
+impl SyslogWriter {
+    pub fn new(target: &SyslogTarget, format: SyslogFormat) -> io::Result<Self> {
+        let transport = match target {
+            SyslogTarget::Udp(addr) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(addr)?;
+                Transport::Udp(socket)
+            }
+            SyslogTarget::Tcp(addr) => Transport::Tcp(addr.clone(), TcpStream::connect(addr)?),
+            SyslogTarget::File(path) => Transport::File(BufWriter::new(File::create(path)?)),
+        };
+        Ok(SyslogWriter {
+            transport,
+            format,
+            failed: 0,
+        })
+    }
+
+    /// Writes the messages to an opened file (ex: an output file of a resumed scan).
+    pub fn from_file(file: File, format: SyslogFormat) -> Self {
+        SyslogWriter {
+            transport: Transport::File(BufWriter::new(file)),
+            format,
+            failed: 0,
+        }
+    }
+
+    /// Sends a detection. Messages that could not be sent are counted instead of stopping the scan.
+    pub fn write(&mut self, json: &Value, rule: &Rule) {
+        let msg = syslog_message(json, rule, self.format);
+        let res = match &mut self.transport {
+            Transport::Udp(socket) => socket.send(msg.as_bytes()).map(|_| ()),
+            Transport::Tcp(addr, stream) => {
+                // octet counting framing (RFC 6587)
+                let frame = format!("{} {}", msg.len(), msg);
+                // the connection may have been closed by the server, so reconnect once
+                stream.write_all(frame.as_bytes()).or_else(|_| {
+                    *stream = TcpStream::connect(addr.as_str())?;
+                    stream.write_all(frame.as_bytes())
+                })
+            }
+            Transport::File(writer) => writeln!(writer, "{}", msg),
+        };
+        if res.is_err() {
+            self.failed += 1;
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.transport {
+            Transport::Udp(_) => Ok(()),
+            Transport::Tcp(_, stream) => stream.flush(),
+            Transport::File(writer) => writer.flush(),
+        }
+    }
+}
+
+fn level(rule: &Rule) -> String {
+    rule.level
+        .as_ref()
+        .map(|l| format!("{:?}", l).to_lowercase())
+        .unwrap_or_default()
+}
+
+fn syslog_severity(level: &str) -> u8 {
+    match level {
+        "critical" => 2,
+        "high" => 3,
+        "medium" => 4,
+        "low" => 5,
+        _ => 6,
+    }
+}
+
+// CEF and LEEF use a 0-10 severity scale.
+fn event_severity(level: &str) -> u8 {
+    match level {
+        "critical" => 10,
+        "high" => 8,
+        "medium" => 5,
+        "low" => 3,
+        _ => 1,
+    }
+}
+
+pub fn syslog_message(json: &Value, rule: &Rule, format: SyslogFormat) -> String {
+    let level = level(rule);
+    let pri = FACILITY * 8 + syslog_severity(&level);
+    let time = get_json_str(json, "eventTime")
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let payload = match format {
+        SyslogFormat::Cef => cef(json, rule, &level, &time),
+        SyslogFormat::Leef => leef(json, rule, &level, &time),
+    };
+    format!(
+        "<{}>1 {} - {} {} detection - {}",
+        pri,
+        time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        APP_NAME,
+        std::process::id(),
+        payload
+    )
+}
+
+fn cef(json: &Value, rule: &Rule, level: &str, time: &DateTime<Utc>) -> String {
+    let get = |key: &str| get_json_str(json, key).unwrap_or("-");
+    let signature = rule.id.clone().unwrap_or(rule.title.clone());
+    let ext = [
+        ("rt", time.timestamp_millis().to_string()),
+        ("src", get("sourceIPAddress").to_string()),
+        ("suser", get("userIdentity.arn").to_string()),
+        ("act", get("eventName").to_string()),
+        ("requestClientApplication", get("userAgent").to_string()),
+        ("cs1Label", "eventSource".to_string()),
+        ("cs1", get("eventSource").to_string()),
+        ("cs2Label", "awsRegion".to_string()),
+        ("cs2", get("awsRegion").to_string()),
+        ("cs3Label", "eventID".to_string()),
+        ("cs3", get("eventID").to_string()),
+        ("cs4Label", "accessKeyId".to_string()),
+        ("cs4", get("userIdentity.accessKeyId").to_string()),
+    ];
+    let ext: Vec<String> = ext
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, cef_ext_escape(v)))
+        .collect();
+    format!(
+        "CEF:0|Yamato Security|Suzaku|{}|{}|{}|{}|{}",
+        VERSION,
+        cef_header_escape(&signature),
+        cef_header_escape(&rule.title),
+        event_severity(level),
+        ext.join(" ")
+    )
+}
+
+fn leef(json: &Value, rule: &Rule, level: &str, time: &DateTime<Utc>) -> String {
+    let get = |key: &str| get_json_str(json, key).unwrap_or("-");
+    let event_id = rule.id.clone().unwrap_or(rule.title.clone());
+    let attrs = [
+        ("cat", level.to_string()),
+        ("sev", event_severity(level).to_string()),
+        ("devTime", time.timestamp_millis().to_string()),
+        ("src", get("sourceIPAddress").to_string()),
+        ("usrName", get("userIdentity.arn").to_string()),
+        ("ruleTitle", rule.title.clone()),
+        ("eventName", get("eventName").to_string()),
+        ("eventSource", get("eventSource").to_string()),
+        ("awsRegion", get("awsRegion").to_string()),
+        ("eventID", get("eventID").to_string()),
+        ("userAgent", get("userAgent").to_string()),
+        ("accessKeyId", get("userIdentity.accessKeyId").to_string()),
+    ];
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v.replace(['\t', '\r', '\n'], " ")))
+        .collect();
+    format!(
+        "LEEF:1.0|Yamato Security|Suzaku|{}|{}|{}",
+        VERSION,
+        event_id.replace(['|', '\t', '\r', '\n'], " "),
+        attrs.join("\t")
+    )
+}
+
+fn cef_header_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+fn cef_ext_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+    use serde_json::json;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    fn read_frame(stream: &mut TcpStream) -> String {
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        let (len, msg) = received.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), msg.len());
+        msg.to_string()
+    }
+
+    #[test]
+    fn test_cef_message() {
+        let msg = syslog_message(&test_event(), &test_rule("high"), SyslogFormat::Cef);
+        assert!(msg.starts_with("<11>1 2024-08-18T13:05:25.000Z - suzaku "));
+        assert!(msg.contains("CEF:0|Yamato Security|Suzaku|"));
+        assert!(msg.contains("|test_rule|CloudTrail Deleted|8|rt=1723986325000 src=192.0.2.1"));
+    }
+
+    #[test]
+    fn test_cef_escape() {
+        let mut rule = test_rule("high");
+        rule.title = "Trail|Deleted".to_string();
+        let mut event = test_event();
+        event["userAgent"] = json!("a=b");
+        let msg = syslog_message(&event, &rule, SyslogFormat::Cef);
+        assert!(msg.contains("|test_rule|Trail\\|Deleted|8|"));
+        assert!(msg.contains("requestClientApplication=a\\=b"));
+    }
+
+    #[test]
+    fn test_leef_message() {
+        let msg = syslog_message(&test_event(), &test_rule("high"), SyslogFormat::Leef);
+        assert!(msg.contains("LEEF:1.0|Yamato Security|Suzaku|"));
+        assert!(msg.contains("|test_rule|cat=high\tsev=8\tdevTime=1723986325000\tsrc=192.0.2.1"));
+    }
+
+    #[test]
+    fn test_udp_transport() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = SyslogTarget::parse(&format!("udp://{}", udp.local_addr().unwrap()));
+        let mut writer = SyslogWriter::new(&target, SyslogFormat::Cef).unwrap();
+        writer.write(&test_event(), &test_rule("high"));
+        let mut buf = [0; 2048];
+        let len = udp.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).contains("CEF:0|"));
+    }
+
+    #[test]
+    fn test_tcp_transport() {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = SyslogTarget::parse(&format!("tcp://{}", tcp.local_addr().unwrap()));
+        let mut writer = SyslogWriter::new(&target, SyslogFormat::Leef).unwrap();
+        writer.write(&test_event(), &test_rule("high"));
+        drop(writer);
+        let msg = read_frame(&mut tcp.accept().unwrap().0);
+        assert!(msg.contains("LEEF:1.0|"));
+    }
+
+    #[test]
+    fn test_tcp_reconnect() {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = SyslogTarget::parse(&format!("tcp://{}", tcp.local_addr().unwrap()));
+        let mut writer = SyslogWriter::new(&target, SyslogFormat::Leef).unwrap();
+        // the server closes the first connection
+        drop(tcp.accept().unwrap().0);
+        tcp.set_nonblocking(true).unwrap();
+        let mut stream = None;
+        for _ in 0..20 {
+            writer.write(&test_event(), &test_rule("high"));
+            if let Ok((s, _)) = tcp.accept() {
+                stream = Some(s);
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let mut stream = stream.expect("the writer did not reconnect");
+        stream.set_nonblocking(false).unwrap();
+        assert_eq!(writer.failed, 0);
+        drop(writer);
+        assert!(read_frame(&mut stream).contains("LEEF:1.0|"));
+    }
+}
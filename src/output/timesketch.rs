@@ -0,0 +1,138 @@
+use crate::core::util::get_json_str;
+use chrono::{DateTime, SecondsFormat, Utc};
+use csv::Writer;
+use serde_json::{Map, Value};
+use sigma_rust::Rule;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const TIMESTAMP_DESC: &str = "Event Time";
+
+/// Writes records in the Timesketch import format.
+/// Each row has the required `message`, `datetime` and `timestamp_desc` fields, followed by
+/// the profile fields as extra attributes.
+pub struct TimesketchWriter {
+    csv: Option<Writer<Box<dyn Write>>>,
+    jsonl: Option<BufWriter<Box<dyn Write>>>,
+}
+
+impl TimesketchWriter {
+    pub fn csv(path: &Path, profile: &[(String, String)]) -> io::Result<Self> {
+        let mut writer = Writer::from_writer(Box::new(File::create(path)?) as Box<dyn Write>);
+        let mut header = vec!["message", "datetime", "timestamp_desc"];
+        header.extend(profile.iter().map(|(k, _)| k.as_str()));
+        writer.write_record(&header)?;
+        Ok(TimesketchWriter {
+            csv: Some(writer),
+            jsonl: None,
+        })
+    }
+
+    pub fn jsonl(path: &Path) -> io::Result<Self> {
+        Ok(TimesketchWriter {
+            csv: None,
+            jsonl: Some(BufWriter::new(Box::new(File::create(path)?))),
+        })
+    }
+
+    pub fn write(
+        &mut self,
+        profile: &[(String, String)],
+        record: &[String],
+        json: &Value,
+        rule: Option<&Rule>,
+    ) -> io::Result<()> {
+        let message = message(json, rule);
+        let datetime = datetime(json);
+        if let Some(writer) = &mut self.csv {
+            let mut row = vec![message.as_str(), datetime.as_str(), TIMESTAMP_DESC];
+            row.extend(record.iter().map(|v| v.as_str()));
+            writer.write_record(&row)?;
+        }
+        if let Some(writer) = &mut self.jsonl {
+            let mut doc = Map::new();
+            doc.insert("message".to_string(), Value::String(message));
+            doc.insert("datetime".to_string(), Value::String(datetime));
+            doc.insert("timestamp_desc".to_string(), TIMESTAMP_DESC.into());
+            for ((k, _), v) in profile.iter().zip(record) {
+                doc.insert(k.clone(), Value::String(v.clone()));
+            }
+            writeln!(writer, "{}", Value::Object(doc))?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(writer) = &mut self.csv {
+            writer.flush()?;
+        }
+        if let Some(writer) = &mut self.jsonl {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// Timesketch expects an ISO 8601 timestamp, so the eventTime is always written in UTC
+// regardless of the time format options.
+fn datetime(json: &Value) -> String {
+    get_json_str(json, "eventTime")
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| {
+            t.with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, false)
+        })
+        .unwrap_or_default()
+}
+
+// ex: [high] CloudTrail Deleted: DeleteTrail (cloudtrail.amazonaws.com) by arn:aws:iam::123456789012:user/alice from 192.0.2.1
+fn message(json: &Value, rule: Option<&Rule>) -> String {
+    let mut message = String::new();
+    if let Some(rule) = rule {
+        if let Some(level) = &rule.level {
+            message.push_str(&format!("[{}] ", format!("{:?}", level).to_lowercase()));
+        }
+        message.push_str(&format!("{}: ", rule.title));
+    }
+    message.push_str(get_json_str(json, "eventName").unwrap_or("-"));
+    if let Some(source) = get_json_str(json, "eventSource") {
+        message.push_str(&format!(" ({})", source));
+    }
+    if let Some(arn) = get_json_str(json, "userIdentity.arn") {
+        message.push_str(&format!(" by {}", arn));
+    }
+    if let Some(ip) = get_json_str(json, "sourceIPAddress") {
+        message.push_str(&format!(" from {}", ip));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::fixture::{test_event, test_rule};
+    use serde_json::json;
+
+    #[test]
+    fn test_datetime() {
+        assert_eq!(datetime(&test_event()), "2024-08-18T13:05:25+00:00");
+        assert_eq!(datetime(&json!({})), "");
+    }
+
+    #[test]
+    fn test_message_with_detection() {
+        assert_eq!(
+            message(&test_event(), Some(&test_rule("high"))),
+            "[high] CloudTrail Deleted: DeleteTrail (cloudtrail.amazonaws.com) by arn:aws:iam::123456789012:user/alice from 192.0.2.1"
+        );
+    }
+
+    #[test]
+    fn test_message_without_detection() {
+        assert_eq!(
+            message(&json!({"eventName": "ListBuckets"}), None),
+            "ListBuckets"
+        );
+    }
+}